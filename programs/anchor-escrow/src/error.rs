@@ -10,4 +10,76 @@ pub enum ErrorCode {
 
     #[msg("Invalid token owner")]
     ConstraintTokenOwner,
+
+    #[msg("Fill amount must be non-zero and at most the remaining deposit")]
+    InvalidFillAmount,
+
+    #[msg("Arithmetic overflow")]
+    MathOverflow,
+
+    #[msg("Escrow has expired")]
+    EscrowExpired,
+
+    #[msg("Take window must not end before it starts")]
+    InvalidTakeWindow,
+
+    #[msg("Escrow has not expired")]
+    EscrowNotExpired,
+
+    #[msg("Taker is not allowed to take this escrow")]
+    TakerNotAllowed,
+
+    #[msg("Non-transferable mints cannot be escrowed")]
+    NonTransferableMint,
+
+    #[msg("Mint has gained risky authorities since the escrow was made")]
+    MintRiskChanged,
+
+    #[msg("Escrow terms do not match what the taker expected")]
+    TermsMismatch,
+
+    #[msg("Withdraw amount must be non-zero and leave part of the deposit in the escrow")]
+    InvalidWithdrawAmount,
+
+    #[msg("Signer is not authorized")]
+    Unauthorized,
+
+    #[msg("Fee basis points exceed 100%")]
+    InvalidFeeBps,
+
+    #[msg("Instruction is paused")]
+    Paused,
+
+    #[msg("Token account required for a non-native leg")]
+    TokenAccountRequired,
+
+    #[msg("Basket needs 1 to 4 legs of distinct mints with nonzero amounts")]
+    InvalidBasket,
+
+    #[msg("Remaining accounts do not match the basket legs")]
+    InvalidBasketAccounts,
+
+    #[msg("Invalid payment option")]
+    InvalidPaymentOption,
+
+    #[msg("Invalid pricing parameters")]
+    InvalidPricing,
+
+    #[msg("Price feed account is invalid")]
+    InvalidPriceFeed,
+
+    #[msg("Price feed is stale")]
+    StalePrice,
+
+    #[msg("Price feed confidence interval is too wide")]
+    PriceConfidenceTooWide,
+
+    #[msg("Vault already holds tokens")]
+    VaultNotEmpty,
+
+    #[msg("Deposit must leave a nonzero amount in escrow")]
+    EmptyDeposit,
+
+    #[msg("Protocol fee exceeds what the taker accepted")]
+    FeeAboveMax,
 }
@@ -0,0 +1,175 @@
+use anchor_lang::prelude::*;
+
+use crate::state::{BasketLeg, Config, Pricing};
+
+#[event]
+pub struct EscrowMade {
+    pub escrow: Pubkey,
+    pub seed: u64,
+    pub maker: Pubkey,
+    pub rent_payer: Pubkey,
+    pub mint_a: Pubkey,
+    pub mint_b: Pubkey,
+    pub deposit: u64,
+    pub receive: u64,
+    pub receive_is_net: bool,
+    pub pricing: Pricing,
+    pub not_before: Option<i64>,
+    pub not_after: Option<i64>,
+    pub allowed_taker: Option<Pubkey>,
+    pub taker_root: Option<[u8; 32]>,
+    pub timestamp: i64,
+}
+
+/// One fill. `amount` is the mint_a released from the vault and `paid` the
+/// mint_b price, both including the protocol fee; `remaining` is zero once the
+/// offer is completely filled.
+#[event]
+pub struct EscrowTaken {
+    pub escrow: Pubkey,
+    pub seed: u64,
+    pub maker: Pubkey,
+    pub taker: Pubkey,
+    pub mint_a: Pubkey,
+    pub mint_b: Pubkey,
+    pub amount: u64,
+    pub paid: u64,
+    pub fee: u64,
+    pub fee_on_mint_a: bool,
+    pub remaining: u64,
+    pub timestamp: i64,
+}
+
+/// The vault was returned to the maker and the escrow closed, either by the
+/// maker or, after expiry, by a keeper collecting `bounty` lamports.
+#[event]
+pub struct EscrowRefunded {
+    pub escrow: Pubkey,
+    pub seed: u64,
+    pub maker: Pubkey,
+    pub mint_a: Pubkey,
+    pub amount: u64,
+    pub keeper: Option<Pubkey>,
+    pub bounty: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct EscrowAmended {
+    pub escrow: Pubkey,
+    pub seed: u64,
+    pub maker: Pubkey,
+    pub old_receive: u64,
+    pub new_receive: u64,
+    pub old_not_before: Option<i64>,
+    pub new_not_before: Option<i64>,
+    pub old_not_after: Option<i64>,
+    pub new_not_after: Option<i64>,
+    pub timestamp: i64,
+}
+
+/// A zero `receive` means the payment option was removed.
+#[event]
+pub struct PaymentOptionSet {
+    pub escrow: Pubkey,
+    pub seed: u64,
+    pub maker: Pubkey,
+    pub mint: Pubkey,
+    pub receive: u64,
+    pub timestamp: i64,
+}
+
+/// `amount` is what reached the vault; `deposit`, `remaining` and `receive` are
+/// the escrow's terms afterwards.
+#[event]
+pub struct EscrowToppedUp {
+    pub escrow: Pubkey,
+    pub seed: u64,
+    pub maker: Pubkey,
+    pub amount: u64,
+    pub deposit: u64,
+    pub remaining: u64,
+    pub receive: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct EscrowWithdrawn {
+    pub escrow: Pubkey,
+    pub seed: u64,
+    pub maker: Pubkey,
+    pub amount: u64,
+    pub deposit: u64,
+    pub remaining: u64,
+    pub receive: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct SurplusSwept {
+    pub escrow: Pubkey,
+    pub seed: u64,
+    pub maker: Pubkey,
+    pub amount: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct BasketMade {
+    pub basket: Pubkey,
+    pub seed: u64,
+    pub maker: Pubkey,
+    pub offered: Vec<BasketLeg>,
+    pub requested: Vec<BasketLeg>,
+    pub not_before: Option<i64>,
+    pub not_after: Option<i64>,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct BasketTaken {
+    pub basket: Pubkey,
+    pub seed: u64,
+    pub maker: Pubkey,
+    pub taker: Pubkey,
+    pub offered: Vec<BasketLeg>,
+    pub requested: Vec<BasketLeg>,
+    pub fee_bps: u16,
+    /// Whether the fee was skimmed from the offered legs rather than the
+    /// requested ones.
+    pub fee_on_offered: bool,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct BasketRefunded {
+    pub basket: Pubkey,
+    pub seed: u64,
+    pub maker: Pubkey,
+    pub offered: Vec<BasketLeg>,
+    pub timestamp: i64,
+}
+
+/// Snapshot of the protocol config after any admin instruction.
+#[event]
+pub struct ConfigUpdated {
+    pub admin: Pubkey,
+    pub treasury: Pubkey,
+    pub fee_bps: u16,
+    pub fee_on_mint_a: bool,
+    pub paused: u8,
+    pub timestamp: i64,
+}
+
+impl ConfigUpdated {
+    pub fn from_config(config: &Config) -> Result<Self> {
+        Ok(Self {
+            admin: config.admin,
+            treasury: config.treasury,
+            fee_bps: config.fee_bps,
+            fee_on_mint_a: config.fee_on_mint_a,
+            paused: config.paused,
+            timestamp: Clock::get()?.unix_timestamp,
+        })
+    }
+}
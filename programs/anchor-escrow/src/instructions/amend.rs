@@ -0,0 +1,59 @@
+use anchor_lang::prelude::*;
+
+use crate::error::ErrorCode;
+use crate::events::EscrowAmended;
+use crate::state::{Config, Escrow, Pricing};
+
+#[derive(Accounts)]
+pub struct Amend<'info> {
+    pub maker: Signer<'info>,
+    #[account(
+        mut,
+        has_one = maker,
+        seeds = [b"escrow", maker.key().as_ref(), escrow.seed.to_le_bytes().as_ref()],
+        bump = escrow.bump,
+    )]
+    pub escrow: Account<'info, Escrow>,
+    #[account(seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, Config>,
+}
+
+impl<'info> Amend<'info> {
+    /// Replaces the price and take window of an open escrow. `receive` is the
+    /// price for whatever is still `remaining`, as after a partial fill. Only
+    /// fixed-price escrows can be amended, since oracle and Dutch escrows take
+    /// their price from `pricing` rather than `receive`.
+    pub fn amend(
+        &mut self,
+        receive: u64,
+        not_before: Option<i64>,
+        not_after: Option<i64>,
+    ) -> Result<()> {
+        require!(
+            self.escrow.pricing == Pricing::Fixed,
+            ErrorCode::InvalidPricing
+        );
+        Escrow::validate_window(not_before, not_after)?;
+
+        let event = EscrowAmended {
+            escrow: self.escrow.key(),
+            seed: self.escrow.seed,
+            maker: self.maker.key(),
+            old_receive: self.escrow.receive,
+            new_receive: receive,
+            old_not_before: self.escrow.not_before,
+            new_not_before: not_before,
+            old_not_after: self.escrow.not_after,
+            new_not_after: not_after,
+            timestamp: Clock::get()?.unix_timestamp,
+        };
+
+        self.escrow.receive = receive;
+        self.escrow.not_before = not_before;
+        self.escrow.not_after = not_after;
+
+        emit!(event);
+
+        Ok(())
+    }
+}
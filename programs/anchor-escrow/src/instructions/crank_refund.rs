@@ -0,0 +1,109 @@
+use anchor_lang::prelude::*;
+use anchor_spl::{
+    associated_token::AssociatedToken,
+    token_interface::{Mint, TokenAccount, TokenInterface},
+};
+
+use crate::error::ErrorCode;
+use crate::events::EscrowRefunded;
+use crate::state::Escrow;
+use crate::utils::{refund_escrow, RefundEscrow};
+
+/// Lamports paid out of the escrow's rent to whoever cranks an expired escrow.
+pub const CRANK_BOUNTY_LAMPORTS: u64 = 500_000;
+
+#[derive(Accounts)]
+pub struct CrankRefund<'info> {
+    #[account(mut)]
+    pub keeper: Signer<'info>,
+    /// CHECK: Bound to the escrow by `has_one`. The keeper cranks on the maker's
+    /// behalf, so it never signs; it only receives a native SOL deposit back.
+    #[account(mut)]
+    pub maker: UncheckedAccount<'info>,
+    #[account(mut, address = escrow.rent_payer)]
+    pub rent_payer: SystemAccount<'info>,
+    #[account(mut)]
+    pub mint_a: InterfaceAccount<'info, Mint>,
+    /// Created at the keeper's expense if the maker has since closed it, so an
+    /// abandoned escrow can still be cranked.
+    #[account(
+        init_if_needed,
+        payer = keeper,
+        associated_token::mint = mint_a,
+        associated_token::authority = maker,
+        associated_token::token_program = token_program,
+    )]
+    pub maker_ata_a: Option<InterfaceAccount<'info, TokenAccount>>,
+    #[account(
+        mut,
+        close = rent_payer,
+        has_one = mint_a,
+        has_one = maker,
+        seeds = [b"escrow", maker.key().as_ref(), escrow.seed.to_le_bytes().as_ref()],
+        bump = escrow.bump,
+    )]
+    pub escrow: Account<'info, Escrow>,
+    #[account(
+        mut,
+        associated_token::mint = mint_a,
+        associated_token::authority = escrow,
+    )]
+    pub vault: Option<InterfaceAccount<'info, TokenAccount>>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub token_program: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
+}
+
+impl<'info> CrankRefund<'info> {
+    pub fn validate(&self) -> Result<()> {
+        let now = Clock::get()?.unix_timestamp;
+
+        require!(self.escrow.is_expired(now), ErrorCode::EscrowNotExpired);
+
+        Ok(())
+    }
+
+    pub fn refund_and_close_vault(
+        &mut self,
+        remaining_accounts: &[AccountInfo<'info>],
+    ) -> Result<u64> {
+        refund_escrow(
+            RefundEscrow {
+                escrow: &self.escrow,
+                mint_a: &self.mint_a,
+                maker: self.maker.to_account_info(),
+                maker_ata_a: self.maker_ata_a.as_ref(),
+                vault: self.vault.as_ref(),
+                rent_payer: self.rent_payer.to_account_info(),
+                token_program: self.token_program.to_account_info(),
+            },
+            remaining_accounts,
+        )
+    }
+
+    /// Moves the keeper's bounty out of the escrow's rent; the rest goes back to
+    /// `rent_payer` when the escrow is closed.
+    pub fn pay_bounty(&mut self) -> Result<u64> {
+        let bounty = CRANK_BOUNTY_LAMPORTS.min(self.escrow.get_lamports());
+
+        self.escrow.sub_lamports(bounty)?;
+        self.keeper.add_lamports(bounty)?;
+
+        Ok(bounty)
+    }
+
+    pub fn emit_refunded(&self, amount: u64, bounty: u64) -> Result<()> {
+        emit!(EscrowRefunded {
+            escrow: self.escrow.key(),
+            seed: self.escrow.seed,
+            maker: self.maker.key(),
+            mint_a: self.mint_a.key(),
+            amount,
+            keeper: Some(self.keeper.key()),
+            bounty,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+}
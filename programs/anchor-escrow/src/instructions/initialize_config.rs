@@ -0,0 +1,54 @@
+use anchor_lang::prelude::*;
+
+use crate::error::ErrorCode;
+use crate::events::ConfigUpdated;
+use crate::program::AnchorEscrow;
+use crate::state::Config;
+
+#[derive(Accounts)]
+pub struct InitializeConfig<'info> {
+    #[account(mut)]
+    pub admin: Signer<'info>,
+    #[account(
+        init,
+        payer = admin,
+        seeds = [b"config"],
+        bump,
+        space = 8 + Config::INIT_SPACE,
+    )]
+    pub config: Account<'info, Config>,
+    #[account(constraint = program.programdata_address()? == Some(program_data.key()))]
+    pub program: Program<'info, AnchorEscrow>,
+    // Only the upgrade authority can claim the admin role
+    #[account(
+        constraint = program_data.upgrade_authority_address == Some(admin.key())
+            @ ErrorCode::Unauthorized,
+    )]
+    pub program_data: Account<'info, ProgramData>,
+    pub system_program: Program<'info, System>,
+}
+
+impl<'info> InitializeConfig<'info> {
+    pub fn init_config(
+        &mut self,
+        treasury: Pubkey,
+        fee_bps: u16,
+        fee_on_mint_a: bool,
+        bumps: &InitializeConfigBumps,
+    ) -> Result<()> {
+        Config::validate_fee(fee_bps)?;
+
+        self.config.set_inner(Config {
+            admin: self.admin.key(),
+            treasury,
+            fee_bps,
+            fee_on_mint_a,
+            paused: 0,
+            bump: bumps.config,
+        });
+
+        emit!(ConfigUpdated::from_config(&self.config)?);
+
+        Ok(())
+    }
+}
@@ -1,16 +1,23 @@
 use anchor_lang::prelude::*;
+use anchor_lang::system_program::{transfer, Transfer};
 use anchor_spl::{
     associated_token::AssociatedToken,
-    token_interface::{transfer_checked, Mint, TokenAccount, TokenInterface, TransferChecked},
+    token_interface::{Mint, TokenAccount, TokenInterface, TransferChecked},
 };
 
-use crate::state::Escrow;
+use crate::error::ErrorCode;
+use crate::events::EscrowMade;
+use crate::state::{Config, Escrow, MakeTerms};
+use crate::utils::{is_native, mint_risk, required, transfer_checked_with_hook, vault_balance};
 
 #[derive(Accounts)]
 #[instruction(seed: u64)]
 pub struct Make<'info> {
     #[account(mut)]
     pub maker: Signer<'info>,
+    /// Funds the escrow's and vault's rent. May be the maker.
+    #[account(mut)]
+    pub payer: Signer<'info>,
     pub mint_a: InterfaceAccount<'info, Mint>,
     pub mint_b: InterfaceAccount<'info, Mint>,
     #[account(
@@ -18,56 +25,123 @@ pub struct Make<'info> {
         associated_token::mint = mint_a,
         associated_token::authority = maker,
     )]
-    pub maker_ata_a: InterfaceAccount<'info, TokenAccount>,
+    pub maker_ata_a: Option<InterfaceAccount<'info, TokenAccount>>,
     #[account(
         init,
-        payer = maker,
+        payer = payer,
         seeds = [b"escrow", maker.key().as_ref(), seed.to_le_bytes().as_ref()],
         bump,
         space = 8 + Escrow::INIT_SPACE,
     )]
     pub escrow: Account<'info, Escrow>,
+    /// The vault's address is predictable from the seed, so it may already have
+    /// been created by someone else; that is fine as long as it is still empty.
     #[account(
-        init,
-        payer = maker,
+        init_if_needed,
+        payer = payer,
         associated_token::mint = mint_a,
         associated_token::authority = escrow,
     )]
-    pub vault: InterfaceAccount<'info, TokenAccount>,
+    pub vault: Option<InterfaceAccount<'info, TokenAccount>>,
+    #[account(seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, Config>,
     pub associated_token_program: Program<'info, AssociatedToken>,
     pub token_program: Interface<'info, TokenInterface>,
     pub system_program: Program<'info, System>,
 }
 
 impl<'info> Make<'info> {
-    pub fn init_escrow(&mut self, seed: u64, receive: u64, bumps: &MakeBumps) -> Result<()> {
+    pub fn init_escrow(&mut self, seed: u64, terms: &MakeTerms, bumps: &MakeBumps) -> Result<()> {
+        Escrow::validate_window(terms.not_before, terms.not_after)?;
+        terms.pricing.validate()?;
+
         let clock = Clock::get()?;
         self.escrow.set_inner(Escrow {
             seed,
             maker: self.maker.key(),
+            rent_payer: self.payer.key(),
             mint_a: self.mint_a.key(),
             mint_b: self.mint_b.key(),
-            receive,
+            deposit: 0,
+            receive: terms.receive,
+            receive_is_net: terms.receive_is_net,
+            remaining: 0,
             created_at: clock.unix_timestamp,
+            not_before: terms.not_before,
+            not_after: terms.not_after,
+            allowed_taker: terms.allowed_taker,
+            taker_root: terms.taker_root,
+            mint_a_risk: mint_risk(&self.mint_a.to_account_info())?,
+            mint_b_risk: mint_risk(&self.mint_b.to_account_info())?,
+            payment_options: vec![],
+            pricing: terms.pricing,
             bump: bumps.escrow,
         });
 
         Ok(())
     }
 
-    pub fn deposit(&mut self, deposit: u64) -> Result<()> {
-        let cpi_program = self.token_program.to_account_info();
+    /// Funds the offer. A native SOL deposit is held as lamports by the escrow
+    /// itself, and needs neither `maker_ata_a` nor `vault`.
+    pub fn deposit(
+        &mut self,
+        deposit: u64,
+        remaining_accounts: &[AccountInfo<'info>],
+    ) -> Result<()> {
+        let deposited = if is_native(&self.mint_a.key()) {
+            let cpi_accounts = Transfer {
+                from: self.maker.to_account_info(),
+                to: self.escrow.to_account_info(),
+            };
+
+            let cpi_ctx = CpiContext::new(self.system_program.to_account_info(), cpi_accounts);
+
+            transfer(cpi_ctx, deposit)?;
+
+            deposit
+        } else {
+            let vault = required(self.vault.as_mut())?;
+            require!(vault.amount == 0, ErrorCode::VaultNotEmpty);
+
+            let cpi_program = self.token_program.to_account_info();
 
-        let cpi_accounts = TransferChecked {
-            from: self.maker_ata_a.to_account_info(),
-            to: self.vault.to_account_info(),
-            authority: self.maker.to_account_info(),
-            mint: self.mint_a.to_account_info(),
+            let cpi_accounts = TransferChecked {
+                from: required(self.maker_ata_a.as_ref())?.to_account_info(),
+                to: vault.to_account_info(),
+                authority: self.maker.to_account_info(),
+                mint: self.mint_a.to_account_info(),
+            };
+
+            let cpi_ctx = CpiContext::new(cpi_program, cpi_accounts)
+                .with_remaining_accounts(remaining_accounts.to_vec());
+
+            transfer_checked_with_hook(cpi_ctx, deposit, self.mint_a.decimals)?;
+
+            vault_balance(&vault.to_account_info())?
         };
+        // An offer of nothing could never be filled
+        require!(deposited > 0, ErrorCode::EmptyDeposit);
 
-        let cpi_ctx = CpiContext::new(cpi_program, cpi_accounts);
+        self.escrow.deposit = deposited;
+        self.escrow.remaining = deposited;
 
-        transfer_checked(cpi_ctx, deposit, self.mint_a.decimals)?;
+        emit!(EscrowMade {
+            escrow: self.escrow.key(),
+            seed: self.escrow.seed,
+            maker: self.maker.key(),
+            rent_payer: self.escrow.rent_payer,
+            mint_a: self.mint_a.key(),
+            mint_b: self.mint_b.key(),
+            deposit: self.escrow.deposit,
+            receive: self.escrow.receive,
+            receive_is_net: self.escrow.receive_is_net,
+            pricing: self.escrow.pricing,
+            not_before: self.escrow.not_before,
+            not_after: self.escrow.not_after,
+            allowed_taker: self.escrow.allowed_taker,
+            taker_root: self.escrow.taker_root,
+            timestamp: self.escrow.created_at,
+        });
 
         Ok(())
     }
@@ -0,0 +1,147 @@
+use anchor_lang::prelude::*;
+use anchor_spl::associated_token::{
+    create_idempotent, get_associated_token_address_with_program_id, AssociatedToken, Create,
+};
+
+use crate::error::ErrorCode;
+use crate::events::BasketMade;
+use crate::state::{Basket, BasketLeg, Config, Escrow};
+use crate::utils::{basket_groups, basket_token_amount, basket_transfer, mint_risk, vault_balance};
+
+/// Remaining accounts per offered leg: `[mint, token_program, maker_ata, vault]`.
+pub const MAKE_BASKET_OFFERED_GROUP: usize = 4;
+/// Remaining accounts per requested leg, after all offered legs:
+/// `[mint, token_program]`.
+pub const MAKE_BASKET_REQUESTED_GROUP: usize = 2;
+
+#[derive(Accounts)]
+#[instruction(seed: u64)]
+pub struct MakeBasket<'info> {
+    #[account(mut)]
+    pub maker: Signer<'info>,
+    #[account(
+        init,
+        payer = maker,
+        seeds = [b"basket", maker.key().as_ref(), seed.to_le_bytes().as_ref()],
+        bump,
+        space = 8 + Basket::INIT_SPACE,
+    )]
+    pub basket: Account<'info, Basket>,
+    #[account(seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, Config>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
+}
+
+impl<'info> MakeBasket<'info> {
+    pub fn init_basket(
+        &mut self,
+        seed: u64,
+        offered: Vec<BasketLeg>,
+        requested: Vec<BasketLeg>,
+        not_before: Option<i64>,
+        not_after: Option<i64>,
+        bumps: &MakeBasketBumps,
+    ) -> Result<()> {
+        Basket::validate_legs(&offered)?;
+        Basket::validate_legs(&requested)?;
+        Escrow::validate_window(not_before, not_after)?;
+
+        self.basket.set_inner(Basket {
+            seed,
+            maker: self.maker.key(),
+            offered,
+            requested,
+            offered_risk: vec![],
+            requested_risk: vec![],
+            created_at: Clock::get()?.unix_timestamp,
+            not_before,
+            not_after,
+            bump: bumps.basket,
+        });
+
+        Ok(())
+    }
+
+    /// Creates a vault for each offered mint and moves the maker's deposit into it,
+    /// recording the risk of every mint the basket offers or requests.
+    pub fn deposit(&mut self, remaining_accounts: &[AccountInfo<'info>]) -> Result<()> {
+        let basket_key = self.basket.key();
+        let split = self.basket.offered.len() * MAKE_BASKET_OFFERED_GROUP;
+        require!(
+            remaining_accounts.len() >= split,
+            ErrorCode::InvalidBasketAccounts
+        );
+        let (offered, requested) = remaining_accounts.split_at(split);
+
+        let groups = basket_groups(offered, &self.basket.offered, MAKE_BASKET_OFFERED_GROUP)?;
+        for (i, group) in groups.into_iter().enumerate() {
+            let [mint, token_program, maker_ata, vault] = group else {
+                return err!(ErrorCode::InvalidBasketAccounts);
+            };
+
+            require_keys_eq!(
+                vault.key(),
+                get_associated_token_address_with_program_id(
+                    &basket_key,
+                    mint.key,
+                    token_program.key
+                ),
+                ErrorCode::InvalidBasketAccounts
+            );
+
+            let cpi_accounts = Create {
+                payer: self.maker.to_account_info(),
+                associated_token: vault.clone(),
+                authority: self.basket.to_account_info(),
+                mint: mint.clone(),
+                system_program: self.system_program.to_account_info(),
+                token_program: token_program.clone(),
+            };
+
+            // A vault created ahead of time is fine as long as it holds nothing
+            create_idempotent(CpiContext::new(
+                self.associated_token_program.to_account_info(),
+                cpi_accounts,
+            ))?;
+            require!(
+                basket_token_amount(vault, mint.key, &basket_key)? == 0,
+                ErrorCode::VaultNotEmpty
+            );
+            self.basket.offered_risk.push(mint_risk(mint)?);
+
+            basket_transfer(
+                group,
+                maker_ata,
+                vault,
+                &self.maker.to_account_info(),
+                self.basket.offered[i].amount,
+                &[],
+            )?;
+
+            self.basket.offered[i].amount = vault_balance(vault)?;
+        }
+
+        let groups = basket_groups(
+            requested,
+            &self.basket.requested,
+            MAKE_BASKET_REQUESTED_GROUP,
+        )?;
+        for group in groups {
+            self.basket.requested_risk.push(mint_risk(&group[0])?);
+        }
+
+        emit!(BasketMade {
+            basket: basket_key,
+            seed: self.basket.seed,
+            maker: self.maker.key(),
+            offered: self.basket.offered.clone(),
+            requested: self.basket.requested.clone(),
+            not_before: self.basket.not_before,
+            not_after: self.basket.not_after,
+            timestamp: self.basket.created_at,
+        });
+
+        Ok(())
+    }
+}
@@ -1,7 +1,29 @@
+pub mod amend;
+pub mod crank_refund;
+pub mod initialize_config;
 pub mod make;
+pub mod make_basket;
 pub mod refund;
+pub mod refund_basket;
+pub mod set_payment_option;
+pub mod sweep;
 pub mod take;
+pub mod take_basket;
+pub mod top_up;
+pub mod update_config;
+pub mod withdraw_partial;
 
+pub use amend::*;
+pub use crank_refund::*;
+pub use initialize_config::*;
 pub use make::*;
+pub use make_basket::*;
 pub use refund::*;
+pub use refund_basket::*;
+pub use set_payment_option::*;
+pub use sweep::*;
 pub use take::*;
+pub use take_basket::*;
+pub use top_up::*;
+pub use update_config::*;
+pub use withdraw_partial::*;
@@ -1,25 +1,27 @@
 use anchor_lang::prelude::*;
-use anchor_spl::token_interface::{
-    close_account, transfer_checked, CloseAccount, Mint, TokenAccount, TokenInterface,
-    TransferChecked,
-};
+use anchor_spl::token_interface::{Mint, TokenAccount, TokenInterface};
 
+use crate::events::EscrowRefunded;
 use crate::state::Escrow;
+use crate::utils::{refund_escrow, RefundEscrow};
 
 #[derive(Accounts)]
 pub struct Refund<'info> {
     #[account(mut)]
     maker: Signer<'info>,
+    #[account(mut, address = escrow.rent_payer)]
+    rent_payer: SystemAccount<'info>,
+    #[account(mut)]
     mint_a: InterfaceAccount<'info, Mint>,
     #[account(
         mut,
         associated_token::mint = mint_a,
         associated_token::authority = maker,
     )]
-    maker_ata_a: InterfaceAccount<'info, TokenAccount>,
+    maker_ata_a: Option<InterfaceAccount<'info, TokenAccount>>,
     #[account(
         mut,
-        close = maker,
+        close = rent_payer,
         has_one = mint_a,
         has_one = maker,
         seeds = [b"escrow", maker.key().as_ref(), escrow.seed.to_le_bytes().as_ref()],
@@ -31,44 +33,41 @@ pub struct Refund<'info> {
         associated_token::mint = mint_a,
         associated_token::authority = escrow,
     )]
-    vault: InterfaceAccount<'info, TokenAccount>,
+    vault: Option<InterfaceAccount<'info, TokenAccount>>,
     token_program: Interface<'info, TokenInterface>,
     system_program: Program<'info, System>,
 }
 
 impl<'info> Refund<'info> {
-    pub fn refund_and_close_vault(&mut self) -> Result<()> {
-        let signer_seeds: [&[&[u8]]; 1] = [&[
-            b"escrow",
-            self.maker.key.as_ref(),
-            &self.escrow.seed.to_le_bytes()[..],
-            &[self.escrow.bump],
-        ]];
-
-        let cpi_program = self.token_program.to_account_info();
-
-        let cpi_accounts = TransferChecked {
-            from: self.vault.to_account_info(),
-            to: self.maker_ata_a.to_account_info(),
-            mint: self.mint_a.to_account_info(),
-            authority: self.escrow.to_account_info(),
-        };
-
-        let cpi_context = CpiContext::new_with_signer(cpi_program, cpi_accounts, &signer_seeds);
-
-        transfer_checked(cpi_context, self.vault.amount, self.mint_a.decimals)?;
-
-        let cpi_program = self.token_program.to_account_info();
-
-        let cpi_accounts = CloseAccount {
-            account: self.vault.to_account_info(),
-            destination: self.maker.to_account_info(),
-            authority: self.escrow.to_account_info(),
-        };
-
-        let cpi_context = CpiContext::new_with_signer(cpi_program, cpi_accounts, &signer_seeds);
+    pub fn refund_and_close_vault(
+        &mut self,
+        remaining_accounts: &[AccountInfo<'info>],
+    ) -> Result<u64> {
+        refund_escrow(
+            RefundEscrow {
+                escrow: &self.escrow,
+                mint_a: &self.mint_a,
+                maker: self.maker.to_account_info(),
+                maker_ata_a: self.maker_ata_a.as_ref(),
+                vault: self.vault.as_ref(),
+                rent_payer: self.rent_payer.to_account_info(),
+                token_program: self.token_program.to_account_info(),
+            },
+            remaining_accounts,
+        )
+    }
 
-        close_account(cpi_context)?;
+    pub fn emit_refunded(&self, amount: u64) -> Result<()> {
+        emit!(EscrowRefunded {
+            escrow: self.escrow.key(),
+            seed: self.escrow.seed,
+            maker: self.maker.key(),
+            mint_a: self.mint_a.key(),
+            amount,
+            keeper: None,
+            bounty: 0,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
 
         Ok(())
     }
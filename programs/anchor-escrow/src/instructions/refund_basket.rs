@@ -0,0 +1,86 @@
+use anchor_lang::prelude::*;
+
+use crate::error::ErrorCode;
+use crate::events::BasketRefunded;
+use crate::state::{Basket, BasketLeg};
+use crate::utils::{basket_groups, basket_token_amount, basket_transfer, close_basket_vault};
+
+/// Remaining accounts per offered leg: `[mint, token_program, vault, maker_ata]`.
+/// `mint` must be writable if it has a transfer fee, see [`close_basket_vault`].
+pub const REFUND_BASKET_GROUP: usize = 4;
+
+#[derive(Accounts)]
+pub struct RefundBasket<'info> {
+    #[account(mut)]
+    pub maker: Signer<'info>,
+    #[account(
+        mut,
+        close = maker,
+        has_one = maker,
+        seeds = [b"basket", maker.key().as_ref(), basket.seed.to_le_bytes().as_ref()],
+        bump = basket.bump,
+    )]
+    pub basket: Account<'info, Basket>,
+}
+
+impl<'info> RefundBasket<'info> {
+    /// Empties every vault back to the maker and closes it.
+    pub fn refund_and_close_vaults(
+        &mut self,
+        remaining_accounts: &[AccountInfo<'info>],
+    ) -> Result<()> {
+        let signer_seeds: [&[&[u8]]; 1] = [&[
+            b"basket",
+            self.maker.key.as_ref(),
+            &self.basket.seed.to_le_bytes()[..],
+            &[self.basket.bump],
+        ]];
+        let basket_key = self.basket.key();
+
+        let groups = basket_groups(
+            remaining_accounts,
+            &self.basket.offered,
+            REFUND_BASKET_GROUP,
+        )?;
+
+        let mut refunded = Vec::with_capacity(groups.len());
+        for (group, leg) in groups.into_iter().zip(&self.basket.offered) {
+            let [_, _, vault, maker_ata] = group else {
+                return err!(ErrorCode::InvalidBasketAccounts);
+            };
+
+            let amount = basket_token_amount(vault, &leg.mint, &basket_key)?;
+            basket_transfer(
+                group,
+                vault,
+                maker_ata,
+                &self.basket.to_account_info(),
+                amount,
+                &signer_seeds,
+            )?;
+
+            close_basket_vault(
+                group,
+                vault,
+                self.maker.to_account_info(),
+                self.basket.to_account_info(),
+                &signer_seeds,
+            )?;
+
+            refunded.push(BasketLeg {
+                mint: leg.mint,
+                amount,
+            });
+        }
+
+        emit!(BasketRefunded {
+            basket: basket_key,
+            seed: self.basket.seed,
+            maker: self.maker.key(),
+            offered: refunded,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+}
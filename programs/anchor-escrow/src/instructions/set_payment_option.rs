@@ -0,0 +1,42 @@
+use anchor_lang::prelude::*;
+use anchor_spl::token_interface::Mint;
+
+use crate::events::PaymentOptionSet;
+use crate::state::{Config, Escrow};
+use crate::utils::mint_risk;
+
+#[derive(Accounts)]
+pub struct SetPaymentOption<'info> {
+    pub maker: Signer<'info>,
+    pub mint: InterfaceAccount<'info, Mint>,
+    #[account(
+        mut,
+        has_one = maker,
+        seeds = [b"escrow", maker.key().as_ref(), escrow.seed.to_le_bytes().as_ref()],
+        bump = escrow.bump,
+    )]
+    pub escrow: Account<'info, Escrow>,
+    #[account(seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, Config>,
+}
+
+impl<'info> SetPaymentOption<'info> {
+    /// Lets takers pay `receive` of `mint` for whatever is still `remaining`,
+    /// as an alternative to mint_b. A zero `receive` withdraws the option.
+    pub fn set_payment_option(&mut self, receive: u64) -> Result<()> {
+        let risk = mint_risk(&self.mint.to_account_info())?;
+        self.escrow
+            .set_payment_option(self.mint.key(), receive, risk)?;
+
+        emit!(PaymentOptionSet {
+            escrow: self.escrow.key(),
+            seed: self.escrow.seed,
+            maker: self.maker.key(),
+            mint: self.mint.key(),
+            receive,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+}
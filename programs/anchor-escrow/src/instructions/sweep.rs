@@ -0,0 +1,88 @@
+use anchor_lang::prelude::*;
+use anchor_spl::token_interface::{Mint, TokenAccount, TokenInterface, TransferChecked};
+
+use crate::events::SurplusSwept;
+use crate::state::Escrow;
+use crate::utils::{is_native, lamports_above_rent, required, transfer_checked_with_hook};
+
+#[derive(Accounts)]
+pub struct Sweep<'info> {
+    #[account(mut)]
+    pub maker: Signer<'info>,
+    pub mint_a: InterfaceAccount<'info, Mint>,
+    #[account(
+        mut,
+        associated_token::mint = mint_a,
+        associated_token::authority = maker,
+    )]
+    pub maker_ata_a: Option<InterfaceAccount<'info, TokenAccount>>,
+    #[account(
+        mut,
+        has_one = mint_a,
+        has_one = maker,
+        seeds = [b"escrow", maker.key().as_ref(), escrow.seed.to_le_bytes().as_ref()],
+        bump = escrow.bump,
+    )]
+    pub escrow: Account<'info, Escrow>,
+    #[account(
+        mut,
+        associated_token::mint = mint_a,
+        associated_token::authority = escrow,
+    )]
+    pub vault: Option<InterfaceAccount<'info, TokenAccount>>,
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+impl<'info> Sweep<'info> {
+    /// Returns any vault balance beyond the escrow's `remaining` to the maker,
+    /// leaving the escrow live. For a native SOL escrow the surplus is whatever
+    /// the escrow holds beyond its rent and `remaining`.
+    pub fn sweep_surplus(&mut self, remaining_accounts: &[AccountInfo<'info>]) -> Result<()> {
+        let surplus = if is_native(&self.mint_a.key()) {
+            let surplus = lamports_above_rent(&self.escrow.to_account_info())?
+                .saturating_sub(self.escrow.remaining);
+            self.escrow.sub_lamports(surplus)?;
+            self.maker.add_lamports(surplus)?;
+            surplus
+        } else {
+            let surplus = required(self.vault.as_ref())?
+                .amount
+                .saturating_sub(self.escrow.remaining);
+            self.sweep_tokens(surplus, remaining_accounts)?;
+            surplus
+        };
+
+        emit!(SurplusSwept {
+            escrow: self.escrow.key(),
+            seed: self.escrow.seed,
+            maker: self.maker.key(),
+            amount: surplus,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    fn sweep_tokens(&self, surplus: u64, remaining_accounts: &[AccountInfo<'info>]) -> Result<()> {
+        let signer_seeds: [&[&[u8]]; 1] = [&[
+            b"escrow",
+            self.maker.key.as_ref(),
+            &self.escrow.seed.to_le_bytes()[..],
+            &[self.escrow.bump],
+        ]];
+
+        let cpi_program = self.token_program.to_account_info();
+
+        let cpi_accounts = TransferChecked {
+            from: required(self.vault.as_ref())?.to_account_info(),
+            to: required(self.maker_ata_a.as_ref())?.to_account_info(),
+            mint: self.mint_a.to_account_info(),
+            authority: self.escrow.to_account_info(),
+        };
+
+        let cpi_context = CpiContext::new_with_signer(cpi_program, cpi_accounts, &signer_seeds)
+            .with_remaining_accounts(remaining_accounts.to_vec());
+
+        transfer_checked_with_hook(cpi_context, surplus, self.mint_a.decimals)
+    }
+}
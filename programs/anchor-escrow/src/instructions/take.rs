@@ -1,121 +1,340 @@
 use anchor_lang::prelude::*;
+use anchor_lang::system_program::{transfer, Transfer};
 use anchor_spl::{
     associated_token::AssociatedToken,
-    token::{close_account, transfer, CloseAccount, Mint, Token, TokenAccount, Transfer},
+    token_interface::{
+        close_account, CloseAccount, Mint, TokenAccount, TokenInterface, TransferChecked,
+    },
 };
 
 use crate::error::ErrorCode;
-use crate::state::Escrow;
+use crate::events::EscrowTaken;
+use crate::state::{Config, Escrow, Pricing};
+use crate::utils::{
+    check_fee_cap, check_mint_risk, gross_transfer_amount, harvest_withheld_fees, is_native,
+    lamports_above_rent, required, transfer_checked_with_hook,
+};
 
 //Create context
 #[derive(Accounts)]
 pub struct Take<'info> {
     #[account(mut)]
     pub taker: Signer<'info>,
+    /// CHECK: Bound to the escrow by `has_one`. Only ever paid: native mint_b
+    /// lamports land here directly, so a program-owned PDA maker works too.
     #[account(mut)]
-    pub maker: SystemAccount<'info>,
-    pub mint_a: Account<'info, Mint>,
-    pub mint_b: Account<'info, Mint>,
-    #[account(mut)]
-    pub taker_ata_a: Account<'info, TokenAccount>,
-    #[account(mut)]
-    pub taker_ata_b: Account<'info, TokenAccount>,
+    pub maker: UncheckedAccount<'info>,
+    /// Gets back the escrow's and vault's rent.
+    #[account(mut, address = escrow.rent_payer)]
+    pub rent_payer: SystemAccount<'info>,
+    #[account(mut, mint::token_program = token_program_a)]
+    pub mint_a: InterfaceAccount<'info, Mint>,
+    /// The mint of the chosen payment option, which need not be `escrow.mint_b`.
+    #[account(mint::token_program = token_program_b)]
+    pub mint_b: InterfaceAccount<'info, Mint>,
+    /// Created for the taker on their first fill of mint_a.
+    #[account(
+        init_if_needed,
+        payer = taker,
+        associated_token::mint = mint_a,
+        associated_token::authority = taker,
+        associated_token::token_program = token_program_a,
+    )]
+    pub taker_ata_a: Option<InterfaceAccount<'info, TokenAccount>>,
     #[account(mut)]
-    pub maker_ata_b: Account<'info, TokenAccount>,
+    pub taker_ata_b: Option<InterfaceAccount<'info, TokenAccount>>,
+    /// Receives any vault surplus on the final fill, created at the taker's
+    /// expense if the maker no longer holds mint_a.
+    #[account(
+        init_if_needed,
+        payer = taker,
+        associated_token::mint = mint_a,
+        associated_token::authority = maker,
+        associated_token::token_program = token_program_a,
+    )]
+    pub maker_ata_a: Option<InterfaceAccount<'info, TokenAccount>>,
+    /// Created at the taker's expense if the maker does not hold mint_b yet.
+    #[account(
+        init_if_needed,
+        payer = taker,
+        associated_token::mint = mint_b,
+        associated_token::authority = maker,
+        associated_token::token_program = token_program_b,
+    )]
+    pub maker_ata_b: Option<InterfaceAccount<'info, TokenAccount>>,
     #[account(
         mut,
-        close = maker,
         has_one = maker,
         has_one = mint_a,
-        has_one = mint_b,
         seeds = [b"escrow", maker.key().as_ref(), escrow.seed.to_le_bytes().as_ref()],
         bump = escrow.bump,
     )]
     pub escrow: Account<'info, Escrow>,
     #[account(mut)]
-    pub vault: Account<'info, TokenAccount>,
+    pub vault: Option<InterfaceAccount<'info, TokenAccount>>,
+    #[account(seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, Config>,
+    #[account(mut)]
+    pub treasury_ata: Option<InterfaceAccount<'info, TokenAccount>>,
+    /// Receives the protocol fee on a native SOL leg.
+    #[account(mut, address = config.treasury)]
+    pub treasury: Option<SystemAccount<'info>>,
+    /// CHECK: Checked against the escrow's oracle pricing when it reads a price.
+    pub price_feed: Option<UncheckedAccount<'info>>,
     pub clock: Sysvar<'info, Clock>,
     pub associated_token_program: Program<'info, AssociatedToken>,
-    pub token_program: Program<'info, Token>,
+    pub token_program_a: Interface<'info, TokenInterface>,
+    pub token_program_b: Interface<'info, TokenInterface>,
     pub system_program: Program<'info, System>,
 }
 
 impl<'info> Take<'info> {
-    pub fn validate(&self) -> Result<()> {
+    pub fn validate(
+        &self,
+        expected_deposit: u64,
+        expected_receive: u64,
+        max_fee_bps: u16,
+        payment_option: u8,
+        proof: &[[u8; 32]],
+    ) -> Result<()> {
         let now = self.clock.unix_timestamp;
+        let payment = self.escrow.payment_terms(payment_option)?;
 
-        const FIVE_DAYS: i64 = 5 * 24 * 60 * 60;
-        // Validate taker_ata_a belongs to taker and uses mint_a
         require_keys_eq!(
-            self.taker_ata_a.owner,
-            self.taker.key(),
-            ErrorCode::ConstraintTokenOwner
-        );
-        require_keys_eq!(
-            self.taker_ata_a.mint,
-            self.mint_a.key(),
-            ErrorCode::ConstraintTokenMint
-        );
-
-        // Validate taker_ata_b belongs to taker and uses mint_b
-        require_keys_eq!(
-            self.taker_ata_b.owner,
-            self.taker.key(),
-            ErrorCode::ConstraintTokenOwner
-        );
-        require_keys_eq!(
-            self.taker_ata_b.mint,
             self.mint_b.key(),
+            payment.mint,
             ErrorCode::ConstraintTokenMint
         );
 
-        // Validate maker_ata_b belongs to maker and uses mint_b
-        require_keys_eq!(
-            self.maker_ata_b.owner,
-            self.maker.key(),
-            ErrorCode::ConstraintTokenOwner
-        );
-        require_keys_eq!(
-            self.maker_ata_b.mint,
-            self.mint_b.key(),
-            ErrorCode::ConstraintTokenMint
-        );
+        if !is_native(&self.mint_a.key()) {
+            required(self.taker_ata_a.as_ref())?;
+            let vault = required(self.vault.as_ref())?;
 
-        // Validate vault belongs to escrow PDA and uses mint_a
-        require_keys_eq!(
-            self.vault.owner,
-            self.escrow.key(),
-            ErrorCode::ConstraintTokenOwner
-        );
-        require_keys_eq!(
-            self.vault.mint,
-            self.mint_a.key(),
-            ErrorCode::ConstraintTokenMint
-        );
+            // Validate vault belongs to escrow PDA and uses mint_a
+            require_keys_eq!(
+                vault.owner,
+                self.escrow.key(),
+                ErrorCode::ConstraintTokenOwner
+            );
+            require_keys_eq!(
+                vault.mint,
+                self.mint_a.key(),
+                ErrorCode::ConstraintTokenMint
+            );
+        }
 
-        require!(
-            now >= self.escrow.created_at + FIVE_DAYS,
-            ErrorCode::TooEarlyToTake,
-        );
+        if !is_native(&self.mint_b.key()) {
+            let taker_ata_b = required(self.taker_ata_b.as_ref())?;
+            required(self.maker_ata_b.as_ref())?;
+
+            // Validate taker_ata_b belongs to taker and uses mint_b
+            require_keys_eq!(
+                taker_ata_b.owner,
+                self.taker.key(),
+                ErrorCode::ConstraintTokenOwner
+            );
+            require_keys_eq!(
+                taker_ata_b.mint,
+                self.mint_b.key(),
+                ErrorCode::ConstraintTokenMint
+            );
+        }
+
+        // Validate treasury_ata belongs to the treasury and uses the fee mint
+        if let Some(treasury_ata) = &self.treasury_ata {
+            require_keys_eq!(
+                treasury_ata.owner,
+                self.config.treasury,
+                ErrorCode::ConstraintTokenOwner
+            );
+            require_keys_eq!(
+                treasury_ata.mint,
+                self.fee_mint(),
+                ErrorCode::ConstraintTokenMint
+            );
+        }
+
+        check_mint_risk(&self.mint_b.to_account_info(), payment.risk)?;
+        check_fee_cap(&self.config, max_fee_bps)?;
+
+        // With `receive_is_net` the taker also covers the mint_b transfer fee
+        let (maker_share, fee) = self.split_payment(self.current_receive(payment_option)?)?;
+        self.escrow.check_terms(
+            expected_deposit,
+            expected_receive,
+            maker_share
+                .checked_add(fee)
+                .ok_or(ErrorCode::MathOverflow)?,
+        )?;
+        self.escrow.check_taker(&self.taker.key(), proof)?;
+        self.escrow.check_window(now)
+    }
+
+    /// Pays the maker (and treasury) for `amount`, returning the mint_b price.
+    pub fn deposit(
+        &mut self,
+        amount: u64,
+        payment_option: u8,
+        remaining_accounts: &[AccountInfo<'info>],
+    ) -> Result<u64> {
+        let receive = self.current_receive(payment_option)?;
+        let price = self.escrow.fill(amount, receive)?;
+        let (maker_share, fee) = self.split_payment(price)?;
+
+        if is_native(&self.mint_b.key()) {
+            self.pay_lamports(self.maker.to_account_info(), maker_share)?;
+
+            if fee > 0 {
+                self.pay_lamports(required(self.treasury.as_ref())?.to_account_info(), fee)?;
+            }
+
+            return Ok(price);
+        }
+
+        self.pay(
+            required(self.maker_ata_b.as_ref())?.to_account_info(),
+            maker_share,
+            remaining_accounts,
+        )?;
+
+        if fee > 0 {
+            self.pay(
+                required(self.treasury_ata.as_ref())?.to_account_info(),
+                fee,
+                remaining_accounts,
+            )?;
+        }
+
+        Ok(price)
+    }
+
+    /// Splits what the taker pays for `price` into the maker's share and the
+    /// venue fee. A `receive_is_net` escrow grosses the maker's share up by the
+    /// mint_b transfer fee.
+    fn split_payment(&self, price: u64) -> Result<(u64, u64)> {
+        let fee = if self.config.fee_on_mint_a {
+            0
+        } else {
+            self.config.fee(price)?
+        };
+
+        let mut maker_share = price - fee;
+        if self.escrow.receive_is_net && !is_native(&self.mint_b.key()) {
+            maker_share = gross_transfer_amount(&self.mint_b, maker_share)?;
+        }
+
+        Ok((maker_share, fee))
+    }
+
+    pub fn emit_taken(&self, amount: u64, paid: u64) -> Result<()> {
+        let fee = if self.config.fee_on_mint_a {
+            self.config.fee(amount)?
+        } else {
+            self.config.fee(paid)?
+        };
+
+        emit!(EscrowTaken {
+            escrow: self.escrow.key(),
+            seed: self.escrow.seed,
+            maker: self.maker.key(),
+            taker: self.taker.key(),
+            mint_a: self.mint_a.key(),
+            mint_b: self.mint_b.key(),
+            amount,
+            paid,
+            fee,
+            fee_on_mint_a: self.config.fee_on_mint_a,
+            remaining: self.escrow.remaining,
+            timestamp: self.clock.unix_timestamp,
+        });
 
         Ok(())
     }
 
-    pub fn deposit(&mut self) -> Result<()> {
-        let cpi_program = self.token_program.to_account_info();
+    /// Price of everything `remaining` in `payment_option` at this moment.
+    fn current_receive(&self, payment_option: u8) -> Result<u64> {
+        let payment = self.escrow.payment_terms(payment_option)?;
+        if payment_option != 0 {
+            return Ok(payment.receive);
+        }
 
-        let cpi_accounts = Transfer {
-            from: self.taker_ata_b.to_account_info(),
-            to: self.maker_ata_b.to_account_info(),
+        match self.escrow.pricing {
+            Pricing::Fixed => Ok(payment.receive),
+            Pricing::Oracle(oracle) => oracle.receive(
+                &self
+                    .price_feed
+                    .as_ref()
+                    .ok_or(ErrorCode::InvalidPriceFeed)?
+                    .to_account_info(),
+                self.escrow.remaining,
+                self.mint_a.decimals,
+                self.mint_b.decimals,
+                self.clock.unix_timestamp,
+            ),
+            Pricing::Dutch(dutch) => dutch.receive(
+                self.escrow.remaining,
+                self.escrow.deposit,
+                self.clock.unix_timestamp,
+            ),
+        }
+    }
+
+    fn pay(
+        &self,
+        to: AccountInfo<'info>,
+        amount: u64,
+        remaining_accounts: &[AccountInfo<'info>],
+    ) -> Result<()> {
+        let cpi_program = self.token_program_b.to_account_info();
+
+        let cpi_accounts = TransferChecked {
+            from: required(self.taker_ata_b.as_ref())?.to_account_info(),
+            to,
             authority: self.taker.to_account_info(),
+            mint: self.mint_b.to_account_info(),
+        };
+
+        let cpi_ctx = CpiContext::new(cpi_program, cpi_accounts)
+            .with_remaining_accounts(remaining_accounts.to_vec());
+
+        transfer_checked_with_hook(cpi_ctx, amount, self.mint_b.decimals)
+    }
+
+    fn pay_lamports(&self, to: AccountInfo<'info>, amount: u64) -> Result<()> {
+        let cpi_accounts = Transfer {
+            from: self.taker.to_account_info(),
+            to,
         };
 
-        let cpi_ctx = CpiContext::new(cpi_program, cpi_accounts);
+        let cpi_ctx = CpiContext::new(self.system_program.to_account_info(), cpi_accounts);
+
+        transfer(cpi_ctx, amount)
+    }
 
-        transfer(cpi_ctx, self.escrow.receive)
+    fn fee_mint(&self) -> Pubkey {
+        if self.config.fee_on_mint_a {
+            self.mint_a.key()
+        } else {
+            self.mint_b.key()
+        }
     }
 
-    pub fn withdraw_and_close_vault(&mut self) -> Result<()> {
+    /// Releases exactly `amount` from the vault to the taker, closing the vault and
+    /// the escrow once the offer has been completely filled, with their rent
+    /// going back to `rent_payer`. Tokens sent to the
+    /// vault outside of `make`/`top_up` are never paid out to takers; whatever
+    /// is left after the last fill goes to the maker before the vault closes.
+    /// A native SOL deposit is paid from the escrow's lamports instead, with
+    /// any surplus above rent likewise going to the maker.
+    pub fn withdraw_and_close_vault(
+        &mut self,
+        amount: u64,
+        remaining_accounts: &[AccountInfo<'info>],
+    ) -> Result<()> {
+        if is_native(&self.mint_a.key()) {
+            return self.release_lamports(amount);
+        }
+
         let signer_seeds: [&[&[u8]]; 1] = [&[
             b"escrow",
             self.maker.key.as_ref(),
@@ -123,28 +342,109 @@ impl<'info> Take<'info> {
             &[self.escrow.bump],
         ]];
 
-        let cpi_program = self.token_program.to_account_info();
+        let fee = if self.config.fee_on_mint_a {
+            self.config.fee(amount)?
+        } else {
+            0
+        };
 
-        let cpi_accounts = Transfer {
-            from: self.vault.to_account_info(),
-            to: self.taker_ata_a.to_account_info(),
+        self.release(
+            required(self.taker_ata_a.as_ref())?.to_account_info(),
+            amount - fee,
+            &signer_seeds,
+            remaining_accounts,
+        )?;
+
+        if fee > 0 {
+            self.release(
+                required(self.treasury_ata.as_ref())?.to_account_info(),
+                fee,
+                &signer_seeds,
+                remaining_accounts,
+            )?;
+        }
+
+        if !self.escrow.is_filled() {
+            return Ok(());
+        }
+
+        let vault = required(self.vault.as_mut())?;
+        vault.reload()?;
+        let surplus = vault.amount;
+        if surplus > 0 {
+            self.release(
+                required(self.maker_ata_a.as_ref())?.to_account_info(),
+                surplus,
+                &signer_seeds,
+                remaining_accounts,
+            )?;
+        }
+        let vault = required(self.vault.as_ref())?.to_account_info();
+
+        harvest_withheld_fees(
+            self.token_program_a.to_account_info(),
+            self.mint_a.to_account_info(),
+            vault.clone(),
+        )?;
+
+        let cpi_program = self.token_program_a.to_account_info();
+
+        let cpi_accounts = CloseAccount {
+            account: vault,
+            destination: self.rent_payer.to_account_info(),
             authority: self.escrow.to_account_info(),
         };
 
         let cpi_context = CpiContext::new_with_signer(cpi_program, cpi_accounts, &signer_seeds);
 
-        transfer(cpi_context, self.vault.amount)?;
+        close_account(cpi_context)?;
 
-        let cpi_program = self.token_program.to_account_info();
+        self.escrow.close(self.rent_payer.to_account_info())
+    }
 
-        let cpi_accounts = CloseAccount {
-            account: self.vault.to_account_info(),
-            destination: self.maker.to_account_info(),
+    fn release_lamports(&mut self, amount: u64) -> Result<()> {
+        let fee = if self.config.fee_on_mint_a {
+            self.config.fee(amount)?
+        } else {
+            0
+        };
+
+        self.escrow.sub_lamports(amount)?;
+        self.taker.add_lamports(amount - fee)?;
+        if fee > 0 {
+            required(self.treasury.as_ref())?.add_lamports(fee)?;
+        }
+
+        if !self.escrow.is_filled() {
+            return Ok(());
+        }
+
+        let surplus = lamports_above_rent(&self.escrow.to_account_info())?;
+        self.escrow.sub_lamports(surplus)?;
+        self.maker.add_lamports(surplus)?;
+
+        self.escrow.close(self.rent_payer.to_account_info())
+    }
+
+    fn release(
+        &self,
+        to: AccountInfo<'info>,
+        amount: u64,
+        signer_seeds: &[&[&[u8]]],
+        remaining_accounts: &[AccountInfo<'info>],
+    ) -> Result<()> {
+        let cpi_program = self.token_program_a.to_account_info();
+
+        let cpi_accounts = TransferChecked {
+            from: required(self.vault.as_ref())?.to_account_info(),
+            to,
             authority: self.escrow.to_account_info(),
+            mint: self.mint_a.to_account_info(),
         };
 
-        let cpi_context = CpiContext::new_with_signer(cpi_program, cpi_accounts, &signer_seeds);
+        let cpi_context = CpiContext::new_with_signer(cpi_program, cpi_accounts, signer_seeds)
+            .with_remaining_accounts(remaining_accounts.to_vec());
 
-        close_account(cpi_context)
+        transfer_checked_with_hook(cpi_context, amount, self.mint_a.decimals)
     }
 }
@@ -0,0 +1,190 @@
+use anchor_lang::prelude::*;
+
+use crate::error::ErrorCode;
+use crate::events::BasketTaken;
+use crate::state::{Basket, Config};
+use crate::utils::{
+    basket_groups, basket_token_amount, basket_transfer, check_fee_cap, check_mint_risk,
+    close_basket_vault,
+};
+
+/// Remaining accounts per requested leg:
+/// `[mint, token_program, taker_ata, maker_ata, treasury_ata]`.
+pub const TAKE_BASKET_REQUESTED_GROUP: usize = 5;
+/// Remaining accounts per offered leg, after all requested legs:
+/// `[mint, token_program, vault, taker_ata, maker_ata, treasury_ata]`.
+///
+/// `treasury_ata` is only read when the protocol fee is charged on that side.
+/// An offered `mint` with a Token-2022 transfer fee must also be writable, as the
+/// vault is closed through [`close_basket_vault`].
+pub const TAKE_BASKET_OFFERED_GROUP: usize = 6;
+
+#[derive(Accounts)]
+pub struct TakeBasket<'info> {
+    #[account(mut)]
+    pub taker: Signer<'info>,
+    #[account(mut)]
+    pub maker: SystemAccount<'info>,
+    #[account(
+        mut,
+        close = maker,
+        has_one = maker,
+        seeds = [b"basket", maker.key().as_ref(), basket.seed.to_le_bytes().as_ref()],
+        bump = basket.bump,
+    )]
+    pub basket: Account<'info, Basket>,
+    #[account(seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, Config>,
+}
+
+impl<'info> TakeBasket<'info> {
+    /// Pays every requested leg to the maker and releases every offered leg to the
+    /// taker in one go, skimming the protocol fee from each leg on the side the
+    /// config charges. Anything sent to a vault beyond the recorded deposit goes
+    /// back to the maker before the vault is closed.
+    pub fn settle(
+        &mut self,
+        max_fee_bps: u16,
+        remaining_accounts: &[AccountInfo<'info>],
+    ) -> Result<()> {
+        self.basket.check_window(Clock::get()?.unix_timestamp)?;
+        check_fee_cap(&self.config, max_fee_bps)?;
+
+        let split = self.basket.requested.len() * TAKE_BASKET_REQUESTED_GROUP;
+        require!(
+            remaining_accounts.len() >= split,
+            ErrorCode::InvalidBasketAccounts
+        );
+        let (requested, offered) = remaining_accounts.split_at(split);
+        let fee_on_offered = self.config.fee_on_mint_a;
+
+        let requested_groups = basket_groups(
+            requested,
+            &self.basket.requested,
+            TAKE_BASKET_REQUESTED_GROUP,
+        )?;
+        let requested_legs = self
+            .basket
+            .requested
+            .iter()
+            .zip(&self.basket.requested_risk);
+        for (group, (leg, risk)) in requested_groups.into_iter().zip(requested_legs) {
+            let [mint, _, taker_ata, maker_ata, treasury_ata] = group else {
+                return err!(ErrorCode::InvalidBasketAccounts);
+            };
+
+            // Requested mints are held to their make-time risk just like offered ones
+            check_mint_risk(mint, *risk)?;
+
+            let fee = if fee_on_offered {
+                0
+            } else {
+                self.config.fee(leg.amount)?
+            };
+
+            basket_token_amount(maker_ata, &leg.mint, self.maker.key)?;
+            basket_transfer(
+                group,
+                taker_ata,
+                maker_ata,
+                &self.taker.to_account_info(),
+                leg.amount - fee,
+                &[],
+            )?;
+
+            if fee > 0 {
+                basket_token_amount(treasury_ata, &leg.mint, &self.config.treasury)?;
+                basket_transfer(
+                    group,
+                    taker_ata,
+                    treasury_ata,
+                    &self.taker.to_account_info(),
+                    fee,
+                    &[],
+                )?;
+            }
+        }
+
+        let signer_seeds: [&[&[u8]]; 1] = [&[
+            b"basket",
+            self.maker.key.as_ref(),
+            &self.basket.seed.to_le_bytes()[..],
+            &[self.basket.bump],
+        ]];
+        let basket_key = self.basket.key();
+
+        let offered_groups =
+            basket_groups(offered, &self.basket.offered, TAKE_BASKET_OFFERED_GROUP)?;
+        let offered_legs = self.basket.offered.iter().zip(&self.basket.offered_risk);
+        for (group, (leg, risk)) in offered_groups.into_iter().zip(offered_legs) {
+            let [mint, _, vault, taker_ata, maker_ata, treasury_ata] = group else {
+                return err!(ErrorCode::InvalidBasketAccounts);
+            };
+
+            check_mint_risk(mint, *risk)?;
+
+            let fee = if fee_on_offered {
+                self.config.fee(leg.amount)?
+            } else {
+                0
+            };
+
+            let held = basket_token_amount(vault, &leg.mint, &basket_key)?;
+            basket_transfer(
+                group,
+                vault,
+                taker_ata,
+                &self.basket.to_account_info(),
+                leg.amount - fee,
+                &signer_seeds,
+            )?;
+
+            if fee > 0 {
+                basket_token_amount(treasury_ata, &leg.mint, &self.config.treasury)?;
+                basket_transfer(
+                    group,
+                    vault,
+                    treasury_ata,
+                    &self.basket.to_account_info(),
+                    fee,
+                    &signer_seeds,
+                )?;
+            }
+
+            let surplus = held.saturating_sub(leg.amount);
+            if surplus > 0 {
+                basket_token_amount(maker_ata, &leg.mint, self.maker.key)?;
+                basket_transfer(
+                    group,
+                    vault,
+                    maker_ata,
+                    &self.basket.to_account_info(),
+                    surplus,
+                    &signer_seeds,
+                )?;
+            }
+
+            close_basket_vault(
+                group,
+                vault,
+                self.maker.to_account_info(),
+                self.basket.to_account_info(),
+                &signer_seeds,
+            )?;
+        }
+
+        emit!(BasketTaken {
+            basket: basket_key,
+            seed: self.basket.seed,
+            maker: self.maker.key(),
+            taker: self.taker.key(),
+            offered: self.basket.offered.clone(),
+            requested: self.basket.requested.clone(),
+            fee_bps: self.config.fee_bps,
+            fee_on_offered,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+}
@@ -0,0 +1,52 @@
+use anchor_lang::prelude::*;
+
+use crate::error::ErrorCode;
+use crate::events::ConfigUpdated;
+use crate::state::Config;
+
+#[derive(Accounts)]
+pub struct UpdateConfig<'info> {
+    pub admin: Signer<'info>,
+    #[account(
+        mut,
+        has_one = admin @ ErrorCode::Unauthorized,
+        seeds = [b"config"],
+        bump = config.bump,
+    )]
+    pub config: Account<'info, Config>,
+}
+
+impl<'info> UpdateConfig<'info> {
+    pub fn update_config(
+        &mut self,
+        treasury: Pubkey,
+        fee_bps: u16,
+        fee_on_mint_a: bool,
+    ) -> Result<()> {
+        Config::validate_fee(fee_bps)?;
+
+        self.config.treasury = treasury;
+        self.config.fee_bps = fee_bps;
+        self.config.fee_on_mint_a = fee_on_mint_a;
+
+        emit!(ConfigUpdated::from_config(&self.config)?);
+
+        Ok(())
+    }
+
+    pub fn set_pause(&mut self, paused: u8) -> Result<()> {
+        self.config.paused = paused;
+
+        emit!(ConfigUpdated::from_config(&self.config)?);
+
+        Ok(())
+    }
+
+    pub fn transfer_admin(&mut self, new_admin: Pubkey) -> Result<()> {
+        self.config.admin = new_admin;
+
+        emit!(ConfigUpdated::from_config(&self.config)?);
+
+        Ok(())
+    }
+}
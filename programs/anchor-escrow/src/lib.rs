@@ -4,11 +4,16 @@
 use anchor_lang::prelude::*;
 
 mod error;
+mod events;
 mod instructions;
-mod state;
+pub mod merkle;
+mod oracle;
+pub mod state;
 mod tests;
+mod utils;
 
 use instructions::*;
+use state::{BasketLeg, MakeTerms, PAUSE_AMEND, PAUSE_MAKE, PAUSE_TAKE, PAUSE_TOP_UP};
 
 declare_id!("FircrADQ2wgGuvpm8qneNCfKM7o5zoHTWnDQxngpTQ3J");
 
@@ -16,18 +21,145 @@ declare_id!("FircrADQ2wgGuvpm8qneNCfKM7o5zoHTWnDQxngpTQ3J");
 pub mod anchor_escrow {
     use super::*;
 
-    pub fn make(ctx: Context<Make>, seed: u64, deposit: u64, receive: u64) -> Result<()> {
-        ctx.accounts.init_escrow(seed, receive, &ctx.bumps)?;
-        ctx.accounts.deposit(deposit)
+    pub fn initialize_config(
+        ctx: Context<InitializeConfig>,
+        treasury: Pubkey,
+        fee_bps: u16,
+        fee_on_mint_a: bool,
+    ) -> Result<()> {
+        ctx.accounts
+            .init_config(treasury, fee_bps, fee_on_mint_a, &ctx.bumps)
     }
 
-    pub fn refund(ctx: Context<Refund>) -> Result<()> {
-        ctx.accounts.refund_and_close_vault()
+    pub fn update_config(
+        ctx: Context<UpdateConfig>,
+        treasury: Pubkey,
+        fee_bps: u16,
+        fee_on_mint_a: bool,
+    ) -> Result<()> {
+        ctx.accounts.update_config(treasury, fee_bps, fee_on_mint_a)
     }
 
-    pub fn take(ctx: Context<Take>) -> Result<()> {
+    pub fn set_pause(ctx: Context<UpdateConfig>, paused: u8) -> Result<()> {
+        ctx.accounts.set_pause(paused)
+    }
+
+    pub fn transfer_admin(ctx: Context<UpdateConfig>, new_admin: Pubkey) -> Result<()> {
+        ctx.accounts.transfer_admin(new_admin)
+    }
+
+    pub fn make<'info>(
+        ctx: Context<'_, '_, '_, 'info, Make<'info>>,
+        seed: u64,
+        terms: MakeTerms,
+    ) -> Result<()> {
+        ctx.accounts.config.check_not_paused(PAUSE_MAKE)?;
+        ctx.accounts.init_escrow(seed, &terms, &ctx.bumps)?;
+        ctx.accounts.deposit(terms.deposit, ctx.remaining_accounts)
+    }
+
+    pub fn amend(
+        ctx: Context<Amend>,
+        receive: u64,
+        not_before: Option<i64>,
+        not_after: Option<i64>,
+    ) -> Result<()> {
+        ctx.accounts.config.check_not_paused(PAUSE_AMEND)?;
+        ctx.accounts.amend(receive, not_before, not_after)
+    }
+
+    pub fn set_payment_option(ctx: Context<SetPaymentOption>, receive: u64) -> Result<()> {
+        ctx.accounts.config.check_not_paused(PAUSE_AMEND)?;
+        ctx.accounts.set_payment_option(receive)
+    }
+
+    pub fn top_up<'info>(
+        ctx: Context<'_, '_, '_, 'info, TopUp<'info>>,
+        amount: u64,
+        scale_receive: bool,
+    ) -> Result<()> {
+        ctx.accounts.config.check_not_paused(PAUSE_TOP_UP)?;
+        ctx.accounts
+            .top_up(amount, scale_receive, ctx.remaining_accounts)
+    }
+
+    pub fn withdraw_partial<'info>(
+        ctx: Context<'_, '_, '_, 'info, WithdrawPartial<'info>>,
+        amount: u64,
+    ) -> Result<()> {
+        ctx.accounts.withdraw(amount, ctx.remaining_accounts)
+    }
+
+    pub fn sweep<'info>(ctx: Context<'_, '_, '_, 'info, Sweep<'info>>) -> Result<()> {
+        ctx.accounts.sweep_surplus(ctx.remaining_accounts)
+    }
+
+    pub fn refund<'info>(ctx: Context<'_, '_, '_, 'info, Refund<'info>>) -> Result<()> {
+        let amount = ctx
+            .accounts
+            .refund_and_close_vault(ctx.remaining_accounts)?;
+        ctx.accounts.emit_refunded(amount)
+    }
+
+    pub fn crank_refund<'info>(ctx: Context<'_, '_, '_, 'info, CrankRefund<'info>>) -> Result<()> {
         ctx.accounts.validate()?;
-        ctx.accounts.deposit()?;
-        ctx.accounts.withdraw_and_close_vault()
+        let amount = ctx
+            .accounts
+            .refund_and_close_vault(ctx.remaining_accounts)?;
+        let bounty = ctx.accounts.pay_bounty()?;
+        ctx.accounts.emit_refunded(amount, bounty)
+    }
+
+    pub fn take<'info>(
+        ctx: Context<'_, '_, '_, 'info, Take<'info>>,
+        amount: u64,
+        expected_deposit: u64,
+        expected_receive: u64,
+        max_fee_bps: u16,
+        payment_option: u8,
+        proof: Vec<[u8; 32]>,
+    ) -> Result<()> {
+        ctx.accounts.config.check_not_paused(PAUSE_TAKE)?;
+        ctx.accounts.validate(
+            expected_deposit,
+            expected_receive,
+            max_fee_bps,
+            payment_option,
+            &proof,
+        )?;
+        let paid = ctx
+            .accounts
+            .deposit(amount, payment_option, ctx.remaining_accounts)?;
+        ctx.accounts.emit_taken(amount, paid)?;
+        ctx.accounts
+            .withdraw_and_close_vault(amount, ctx.remaining_accounts)
+    }
+
+    pub fn make_basket<'info>(
+        ctx: Context<'_, '_, '_, 'info, MakeBasket<'info>>,
+        seed: u64,
+        offered: Vec<BasketLeg>,
+        requested: Vec<BasketLeg>,
+        not_before: Option<i64>,
+        not_after: Option<i64>,
+    ) -> Result<()> {
+        ctx.accounts.config.check_not_paused(PAUSE_MAKE)?;
+        ctx.accounts
+            .init_basket(seed, offered, requested, not_before, not_after, &ctx.bumps)?;
+        ctx.accounts.deposit(ctx.remaining_accounts)
+    }
+
+    pub fn take_basket<'info>(
+        ctx: Context<'_, '_, '_, 'info, TakeBasket<'info>>,
+        max_fee_bps: u16,
+    ) -> Result<()> {
+        ctx.accounts.config.check_not_paused(PAUSE_TAKE)?;
+        ctx.accounts.settle(max_fee_bps, ctx.remaining_accounts)
+    }
+
+    pub fn refund_basket<'info>(
+        ctx: Context<'_, '_, '_, 'info, RefundBasket<'info>>,
+    ) -> Result<()> {
+        ctx.accounts.refund_and_close_vaults(ctx.remaining_accounts)
     }
 }
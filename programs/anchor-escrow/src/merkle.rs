@@ -0,0 +1,74 @@
+//! Merkle allowlists of takers. Leaves and inner nodes are domain-separated
+//! SHA-256 hashes, and each pair is hashed in sorted order so proofs need no
+//! left/right flags.
+
+use anchor_lang::prelude::*;
+use solana_sha256_hasher::hashv;
+
+const LEAF_PREFIX: &[u8] = &[0];
+const NODE_PREFIX: &[u8] = &[1];
+
+pub fn leaf(taker: &Pubkey) -> [u8; 32] {
+    hashv(&[LEAF_PREFIX, taker.as_ref()]).to_bytes()
+}
+
+fn node(a: &[u8; 32], b: &[u8; 32]) -> [u8; 32] {
+    let (left, right) = if a <= b { (a, b) } else { (b, a) };
+    hashv(&[NODE_PREFIX, left, right]).to_bytes()
+}
+
+/// Whether `proof` links `taker`'s leaf to `root`.
+pub fn verify(root: &[u8; 32], taker: &Pubkey, proof: &[[u8; 32]]) -> bool {
+    proof
+        .iter()
+        .fold(leaf(taker), |hash, sibling| node(&hash, sibling))
+        == *root
+}
+
+/// Builds allowlist roots and proofs off-chain. A node without a sibling is
+/// carried up to the next level unchanged.
+#[cfg(not(target_os = "solana"))]
+pub struct MerkleTree {
+    layers: Vec<Vec<[u8; 32]>>,
+}
+
+#[cfg(not(target_os = "solana"))]
+impl MerkleTree {
+    pub fn new(takers: &[Pubkey]) -> Self {
+        assert!(!takers.is_empty(), "allowlist must not be empty");
+
+        let mut layers = vec![takers.iter().map(leaf).collect::<Vec<_>>()];
+        while layers.last().unwrap().len() > 1 {
+            let next = layers
+                .last()
+                .unwrap()
+                .chunks(2)
+                .map(|pair| match pair {
+                    [a, b] => node(a, b),
+                    [a] => *a,
+                    _ => unreachable!(),
+                })
+                .collect();
+            layers.push(next);
+        }
+
+        Self { layers }
+    }
+
+    pub fn root(&self) -> [u8; 32] {
+        self.layers.last().unwrap()[0]
+    }
+
+    /// Proof for the taker at `index` in the list the tree was built from.
+    pub fn proof(&self, mut index: usize) -> Vec<[u8; 32]> {
+        let mut proof = vec![];
+        for layer in &self.layers[..self.layers.len() - 1] {
+            if let Some(sibling) = layer.get(index ^ 1) {
+                proof.push(*sibling);
+            }
+            index /= 2;
+        }
+
+        proof
+    }
+}
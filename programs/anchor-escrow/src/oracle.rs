@@ -0,0 +1,78 @@
+use anchor_lang::prelude::*;
+
+use crate::error::ErrorCode;
+
+/// Pyth pull-oracle receiver program, the owner of `PriceUpdateV2` accounts.
+pub const PYTH_RECEIVER_PROGRAM_ID: Pubkey = pubkey!("rec5EKMGg6MxZYaMdyBfgwp4d5rB9T1VQH5pJv5LtFJ");
+
+/// Anchor discriminator of `PriceUpdateV2`, `sha256("account:PriceUpdateV2")[..8]`.
+pub const PRICE_UPDATE_V2_DISCRIMINATOR: [u8; 8] = [34, 241, 35, 99, 157, 126, 244, 205];
+
+/// Mirrors of the Pyth receiver's account layout, so the program does not need
+/// the Pyth SDK to read a price.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum VerificationLevel {
+    Partial { num_signatures: u8 },
+    Full,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug)]
+pub struct PriceFeedMessage {
+    pub feed_id: [u8; 32],
+    pub price: i64,
+    pub conf: u64,
+    pub exponent: i32,
+    pub publish_time: i64,
+    pub prev_publish_time: i64,
+    pub ema_price: i64,
+    pub ema_conf: u64,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug)]
+pub struct PriceUpdateV2 {
+    pub write_authority: Pubkey,
+    pub verification_level: VerificationLevel,
+    pub price_message: PriceFeedMessage,
+    pub posted_slot: u64,
+}
+
+/// Reads a fully verified price from a `PriceUpdateV2` account, rejecting it if
+/// it was published more than `max_age` seconds before `now`, or if its
+/// confidence interval is wider than `max_confidence_bps` of the price.
+pub fn read_price(
+    feed: &AccountInfo,
+    now: i64,
+    max_age: u32,
+    max_confidence_bps: u16,
+) -> Result<PriceFeedMessage> {
+    require_keys_eq!(
+        *feed.owner,
+        PYTH_RECEIVER_PROGRAM_ID,
+        ErrorCode::InvalidPriceFeed
+    );
+
+    let data = feed.try_borrow_data()?;
+    require!(
+        data.len() > 8 && data[..8] == PRICE_UPDATE_V2_DISCRIMINATOR,
+        ErrorCode::InvalidPriceFeed
+    );
+    let update = PriceUpdateV2::deserialize(&mut &data[8..])?;
+
+    require!(
+        update.verification_level == VerificationLevel::Full,
+        ErrorCode::InvalidPriceFeed
+    );
+
+    let message = update.price_message;
+    require!(message.price > 0, ErrorCode::InvalidPriceFeed);
+    require!(
+        now.saturating_sub(message.publish_time) <= max_age as i64,
+        ErrorCode::StalePrice
+    );
+    require!(
+        (message.conf as u128) * 10_000 <= (message.price as u128) * max_confidence_bps as u128,
+        ErrorCode::PriceConfidenceTooWide
+    );
+
+    Ok(message)
+}
@@ -0,0 +1,65 @@
+use anchor_lang::prelude::*;
+
+use crate::error::ErrorCode;
+
+/// Most distinct mints a basket can offer, and separately request.
+pub const MAX_BASKET_LEGS: usize = 4;
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, InitSpace, Debug, PartialEq, Eq)]
+pub struct BasketLeg {
+    pub mint: Pubkey,
+    pub amount: u64,
+}
+
+/// An all-or-nothing offer of several mints for several others. Each offered
+/// mint is held in its own vault, the basket's ATA for that mint.
+#[account]
+#[derive(InitSpace, Debug)]
+pub struct Basket {
+    pub seed: u64,
+    pub maker: Pubkey,
+    /// Amounts held in the vaults, net of transfer fees.
+    #[max_len(MAX_BASKET_LEGS)]
+    pub offered: Vec<BasketLeg>,
+    #[max_len(MAX_BASKET_LEGS)]
+    pub requested: Vec<BasketLeg>,
+    /// `MINT_RISK_*` flags of each offered and requested mint at make time.
+    #[max_len(MAX_BASKET_LEGS)]
+    pub offered_risk: Vec<u8>,
+    #[max_len(MAX_BASKET_LEGS)]
+    pub requested_risk: Vec<u8>,
+    pub created_at: i64,
+    pub not_before: Option<i64>,
+    pub not_after: Option<i64>,
+    pub bump: u8,
+}
+
+impl Basket {
+    pub fn validate_legs(legs: &[BasketLeg]) -> Result<()> {
+        require!(
+            !legs.is_empty() && legs.len() <= MAX_BASKET_LEGS,
+            ErrorCode::InvalidBasket
+        );
+
+        for (i, leg) in legs.iter().enumerate() {
+            require!(leg.amount > 0, ErrorCode::InvalidBasket);
+            require!(
+                legs[..i].iter().all(|other| other.mint != leg.mint),
+                ErrorCode::InvalidBasket
+            );
+        }
+
+        Ok(())
+    }
+
+    pub fn check_window(&self, now: i64) -> Result<()> {
+        if let Some(not_before) = self.not_before {
+            require!(now >= not_before, ErrorCode::TooEarlyToTake);
+        }
+        if let Some(not_after) = self.not_after {
+            require!(now <= not_after, ErrorCode::EscrowExpired);
+        }
+
+        Ok(())
+    }
+}
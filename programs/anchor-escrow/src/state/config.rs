@@ -0,0 +1,54 @@
+use anchor_lang::prelude::*;
+
+use crate::error::ErrorCode;
+
+pub const MAX_FEE_BPS: u16 = 10_000;
+
+/// Bits of `Config::paused`. `PAUSE_ALL` stops every pausable instruction;
+/// refunds and withdrawals back to the maker are never paused.
+pub const PAUSE_ALL: u8 = 1 << 0;
+pub const PAUSE_MAKE: u8 = 1 << 1;
+pub const PAUSE_TAKE: u8 = 1 << 2;
+pub const PAUSE_AMEND: u8 = 1 << 3;
+pub const PAUSE_TOP_UP: u8 = 1 << 4;
+
+#[account]
+#[derive(InitSpace, Debug)]
+pub struct Config {
+    pub admin: Pubkey,
+    /// Owner of the token accounts protocol fees are paid into.
+    pub treasury: Pubkey,
+    pub fee_bps: u16,
+    /// Whether the fee is skimmed from the mint_a the taker receives rather
+    /// than from the mint_b the maker receives.
+    pub fee_on_mint_a: bool,
+    pub paused: u8,
+    pub bump: u8,
+}
+
+impl Config {
+    pub fn validate_fee(fee_bps: u16) -> Result<()> {
+        require!(fee_bps <= MAX_FEE_BPS, ErrorCode::InvalidFeeBps);
+
+        Ok(())
+    }
+
+    pub fn check_not_paused(&self, instruction: u8) -> Result<()> {
+        require!(
+            self.paused & (PAUSE_ALL | instruction) == 0,
+            ErrorCode::Paused
+        );
+
+        Ok(())
+    }
+
+    /// Protocol fee owed on `amount`, rounded down.
+    pub fn fee(&self, amount: u64) -> Result<u64> {
+        let fee = (amount as u128)
+            .checked_mul(self.fee_bps as u128)
+            .ok_or(ErrorCode::MathOverflow)?
+            / MAX_FEE_BPS as u128;
+
+        Ok(fee as u64)
+    }
+}
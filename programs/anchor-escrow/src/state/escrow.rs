@@ -1,13 +1,277 @@
 use anchor_lang::prelude::*;
 
+use crate::error::ErrorCode;
+use crate::merkle;
+use crate::state::Pricing;
+
+/// Bits of `Escrow::mint_a_risk` and `Escrow::mint_b_risk`, describing what the
+/// mint's authorities can still do to tokens once they are escrowed or paid.
+pub const MINT_RISK_FREEZE_AUTHORITY: u8 = 1 << 0;
+pub const MINT_RISK_PERMANENT_DELEGATE: u8 = 1 << 1;
+pub const MINT_RISK_CLOSE_AUTHORITY: u8 = 1 << 2;
+pub const MINT_RISK_TRANSFER_HOOK: u8 = 1 << 3;
+
+/// Most payment mints an escrow accepts besides `mint_b`.
+pub const MAX_PAYMENT_OPTIONS: usize = 3;
+
+/// An alternative to `mint_b`/`receive`, priced for the same `remaining` mint_a.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, InitSpace, Debug, PartialEq, Eq)]
+pub struct PaymentOption {
+    pub mint: Pubkey,
+    pub receive: u64,
+    /// `MINT_RISK_*` flags of `mint` when the option was set.
+    pub risk: u8,
+}
+
+/// What a maker offers and asks for in `make`. Each field seeds the `Escrow`
+/// field of the same name, except `deposit`, which is recorded net of transfer fees.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct MakeTerms {
+    pub deposit: u64,
+    pub receive: u64,
+    pub receive_is_net: bool,
+    pub not_before: Option<i64>,
+    pub not_after: Option<i64>,
+    pub allowed_taker: Option<Pubkey>,
+    pub taker_root: Option<[u8; 32]>,
+    pub pricing: Pricing,
+}
+
 #[account]
 #[derive(InitSpace, Debug)]
 pub struct Escrow {
     pub seed: u64,
     pub maker: Pubkey,
+    /// Who paid the escrow's and vault's rent, and gets it back when they close.
+    pub rent_payer: Pubkey,
     pub mint_a: Pubkey,
     pub mint_b: Pubkey,
+    /// Total mint_a the maker has committed to this offer, net of transfer fees.
+    /// Adjusted by top-ups and withdrawals, but not by fills.
+    pub deposit: u64,
+    /// Amount of mint_b still owed for the `remaining` mint_a, under `Pricing::Fixed`.
     pub receive: u64,
+    /// Whether `receive` is what the maker must end up with after any mint_b
+    /// transfer fee, rather than what the taker sends. Protocol fees on the
+    /// mint_b leg still come out of the maker's side either way.
+    pub receive_is_net: bool,
+    /// Amount of mint_a still available to takers.
+    pub remaining: u64,
     pub created_at: i64,
+    /// Earliest timestamp at which the escrow can be taken, if any.
+    pub not_before: Option<i64>,
+    /// Last timestamp at which the escrow can be taken, if any.
+    pub not_after: Option<i64>,
+    /// The only taker allowed to fill the escrow, if it is private.
+    pub allowed_taker: Option<Pubkey>,
+    /// Root of a `merkle` tree of the takers allowed to fill the escrow, if any.
+    pub taker_root: Option<[u8; 32]>,
+    pub mint_a_risk: u8,
+    pub mint_b_risk: u8,
+    /// Other mints the taker may pay in instead of mint_b, each scaled by fills
+    /// like `receive`. Payment option 0 is `mint_b` itself, option `i` is entry `i - 1`.
+    #[max_len(MAX_PAYMENT_OPTIONS)]
+    pub payment_options: Vec<PaymentOption>,
+    /// How payment option 0 is priced.
+    pub pricing: Pricing,
     pub bump: u8,
 }
+
+/// `value` scaled by `amount / total`, rounded up when `round_up` is set.
+fn pro_rata(value: u64, amount: u64, total: u64, round_up: bool) -> Result<u64> {
+    let numerator = (value as u128)
+        .checked_mul(amount as u128)
+        .ok_or(ErrorCode::MathOverflow)?;
+    let denominator = total as u128;
+    require!(denominator > 0, ErrorCode::MathOverflow);
+
+    let value = if round_up {
+        numerator.div_ceil(denominator)
+    } else {
+        numerator / denominator
+    };
+
+    Ok(u64::try_from(value).map_err(|_| ErrorCode::MathOverflow)?)
+}
+
+impl Escrow {
+    pub fn validate_window(not_before: Option<i64>, not_after: Option<i64>) -> Result<()> {
+        if let (Some(not_before), Some(not_after)) = (not_before, not_after) {
+            require!(not_before <= not_after, ErrorCode::InvalidTakeWindow);
+        }
+
+        Ok(())
+    }
+
+    pub fn is_expired(&self, now: i64) -> bool {
+        self.not_after.is_some_and(|not_after| now > not_after)
+    }
+
+    /// Mint, price and recorded risk of payment option `index`.
+    pub fn payment_terms(&self, index: u8) -> Result<PaymentOption> {
+        if index == 0 {
+            return Ok(PaymentOption {
+                mint: self.mint_b,
+                receive: self.receive,
+                risk: self.mint_b_risk,
+            });
+        }
+
+        self.payment_options
+            .get(index as usize - 1)
+            .copied()
+            .ok_or_else(|| error!(ErrorCode::InvalidPaymentOption))
+    }
+
+    /// Adds, reprices or, with a zero `receive`, removes an alternative payment mint.
+    pub fn set_payment_option(&mut self, mint: Pubkey, receive: u64, risk: u8) -> Result<()> {
+        require!(
+            mint != self.mint_a && mint != self.mint_b,
+            ErrorCode::InvalidPaymentOption
+        );
+
+        let existing = self.payment_options.iter().position(|o| o.mint == mint);
+        match (existing, receive) {
+            (Some(i), 0) => {
+                self.payment_options.remove(i);
+            }
+            (Some(i), _) => {
+                self.payment_options[i] = PaymentOption {
+                    mint,
+                    receive,
+                    risk,
+                };
+            }
+            (None, 0) => return err!(ErrorCode::InvalidPaymentOption),
+            (None, _) => {
+                require!(
+                    self.payment_options.len() < MAX_PAYMENT_OPTIONS,
+                    ErrorCode::InvalidPaymentOption
+                );
+                self.payment_options.push(PaymentOption {
+                    mint,
+                    receive,
+                    risk,
+                });
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Guards the taker against terms changing between signing and execution:
+    /// `receive`, the current price of everything `remaining`, may not exceed
+    /// what the taker expected to pay for it.
+    pub fn check_terms(
+        &self,
+        expected_deposit: u64,
+        expected_receive: u64,
+        receive: u64,
+    ) -> Result<()> {
+        require!(
+            self.remaining == expected_deposit && receive <= expected_receive,
+            ErrorCode::TermsMismatch
+        );
+
+        Ok(())
+    }
+
+    pub fn check_taker(&self, taker: &Pubkey, proof: &[[u8; 32]]) -> Result<()> {
+        if let Some(allowed_taker) = self.allowed_taker {
+            require_keys_eq!(*taker, allowed_taker, ErrorCode::TakerNotAllowed);
+        }
+        if let Some(root) = self.taker_root {
+            require!(
+                merkle::verify(&root, taker, proof),
+                ErrorCode::TakerNotAllowed
+            );
+        }
+
+        Ok(())
+    }
+
+    pub fn check_window(&self, now: i64) -> Result<()> {
+        if let Some(not_before) = self.not_before {
+            require!(now >= not_before, ErrorCode::TooEarlyToTake);
+        }
+        require!(!self.is_expired(now), ErrorCode::EscrowExpired);
+
+        Ok(())
+    }
+
+    /// Books a fill of `amount` mint_a and returns what the taker owes for it,
+    /// given `receive` as the current price of everything `remaining` in the
+    /// chosen payment option. Every stored price is reduced by its share of the fill.
+    ///
+    /// The price is pro-rata to the outstanding terms and rounded up in the maker's
+    /// favour, so the fill that empties the escrow pays exactly what is left of `receive`.
+    pub fn fill(&mut self, amount: u64, receive: u64) -> Result<u64> {
+        require!(
+            amount > 0 && amount <= self.remaining,
+            ErrorCode::InvalidFillAmount
+        );
+
+        let pay = pro_rata(receive, amount, self.remaining, true)?;
+
+        self.receive -= pro_rata(self.receive, amount, self.remaining, true)?;
+        for option in &mut self.payment_options {
+            option.receive -= pro_rata(option.receive, amount, self.remaining, true)?;
+        }
+        self.remaining -= amount;
+
+        Ok(pay)
+    }
+
+    /// Adds `amount` mint_a to the offer, growing every price in proportion when
+    /// `scale_receive` is set and otherwise leaving the total price unchanged.
+    pub fn top_up(&mut self, amount: u64, scale_receive: bool) -> Result<()> {
+        if scale_receive {
+            let extra = pro_rata(self.receive, amount, self.remaining, true)?;
+            self.receive = self
+                .receive
+                .checked_add(extra)
+                .ok_or(ErrorCode::MathOverflow)?;
+
+            for option in &mut self.payment_options {
+                let extra = pro_rata(option.receive, amount, self.remaining, true)?;
+                option.receive = option
+                    .receive
+                    .checked_add(extra)
+                    .ok_or(ErrorCode::MathOverflow)?;
+            }
+        }
+
+        self.deposit = self
+            .deposit
+            .checked_add(amount)
+            .ok_or(ErrorCode::MathOverflow)?;
+        self.remaining = self
+            .remaining
+            .checked_add(amount)
+            .ok_or(ErrorCode::MathOverflow)?;
+
+        Ok(())
+    }
+
+    /// Takes `amount` mint_a out of the offer, shrinking every price in proportion
+    /// so the price of what is left is unchanged.
+    pub fn withdraw(&mut self, amount: u64) -> Result<()> {
+        require!(
+            amount > 0 && amount < self.remaining,
+            ErrorCode::InvalidWithdrawAmount
+        );
+
+        self.receive -= pro_rata(self.receive, amount, self.remaining, false)?;
+        for option in &mut self.payment_options {
+            option.receive -= pro_rata(option.receive, amount, self.remaining, false)?;
+        }
+        self.deposit -= amount;
+        self.remaining -= amount;
+
+        Ok(())
+    }
+
+    pub fn is_filled(&self) -> bool {
+        self.remaining == 0
+    }
+}
@@ -0,0 +1,127 @@
+use anchor_lang::prelude::*;
+
+use crate::error::ErrorCode;
+use crate::oracle::read_price;
+
+/// How much mint_b the escrow asks for its `remaining` mint_a. Alternative
+/// payment options are always priced like `Fixed`.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, InitSpace, Debug, PartialEq, Eq)]
+pub enum Pricing {
+    /// `Escrow::receive`, scaled down by fills.
+    Fixed,
+    /// Read from a price feed at take time.
+    Oracle(OraclePricing),
+    /// Falls linearly over time from a starting to a floor price.
+    Dutch(DutchPricing),
+}
+
+/// Prices mint_a off a Pyth price feed quoting whole mint_a in whole mint_b.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, InitSpace, Debug, PartialEq, Eq)]
+pub struct OraclePricing {
+    /// The `PriceUpdateV2` account to read.
+    pub feed: Pubkey,
+    /// Pyth feed id the account must carry a price for.
+    pub feed_id: [u8; 32],
+    /// Premium over the feed price the maker asks for, in basis points.
+    pub spread_bps: u16,
+    /// Oldest price accepted, in seconds.
+    pub max_age: u32,
+    /// Widest confidence interval accepted, in basis points of the price.
+    pub max_confidence_bps: u16,
+}
+
+/// A descending-price auction. Prices are for the escrow's whole `deposit`, so
+/// partial fills, top-ups and withdrawals keep the unit price on the curve.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, InitSpace, Debug, PartialEq, Eq)]
+pub struct DutchPricing {
+    /// Price until `start_ts`.
+    pub start_receive: u64,
+    /// Price from `end_ts` on.
+    pub end_receive: u64,
+    pub start_ts: i64,
+    pub end_ts: i64,
+}
+
+impl Pricing {
+    pub fn validate(&self) -> Result<()> {
+        match self {
+            Pricing::Fixed => {}
+            Pricing::Oracle(oracle) => {
+                require!(oracle.max_age > 0, ErrorCode::InvalidPricing);
+            }
+            Pricing::Dutch(dutch) => {
+                require!(
+                    dutch.start_ts < dutch.end_ts
+                        && dutch.end_receive > 0
+                        && dutch.end_receive <= dutch.start_receive,
+                    ErrorCode::InvalidPricing
+                );
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl OraclePricing {
+    /// Price of `amount` mint_a in mint_b base units at the feed's current price
+    /// plus the spread, rounded up in the maker's favour.
+    pub fn receive(
+        &self,
+        feed: &AccountInfo,
+        amount: u64,
+        decimals_a: u8,
+        decimals_b: u8,
+        now: i64,
+    ) -> Result<u64> {
+        require_keys_eq!(feed.key(), self.feed, ErrorCode::InvalidPriceFeed);
+        let price = read_price(feed, now, self.max_age, self.max_confidence_bps)?;
+        // The same account can be posted with an update for any feed
+        require!(price.feed_id == self.feed_id, ErrorCode::InvalidPriceFeed);
+
+        // amount * price * 10^exponent * 10^decimals_b / 10^decimals_a, with the spread
+        let mut numerator = (amount as u128)
+            .checked_mul(price.price as u128)
+            .and_then(|n| n.checked_mul(10_000 + self.spread_bps as u128))
+            .ok_or(ErrorCode::MathOverflow)?;
+        let mut denominator = 10_000u128;
+
+        let scale = decimals_b as i32 - decimals_a as i32 + price.exponent;
+        let factor = 10u128
+            .checked_pow(scale.unsigned_abs())
+            .ok_or(ErrorCode::MathOverflow)?;
+        if scale >= 0 {
+            numerator = numerator
+                .checked_mul(factor)
+                .ok_or(ErrorCode::MathOverflow)?;
+        } else {
+            denominator = denominator
+                .checked_mul(factor)
+                .ok_or(ErrorCode::MathOverflow)?;
+        }
+
+        Ok(u64::try_from(numerator.div_ceil(denominator)).map_err(|_| ErrorCode::MathOverflow)?)
+    }
+}
+
+impl DutchPricing {
+    /// Price of `amount` out of a `deposit` mint_a at time `now`, rounded up in
+    /// the maker's favour.
+    pub fn receive(&self, amount: u64, deposit: u64, now: i64) -> Result<u64> {
+        let now = now.clamp(self.start_ts, self.end_ts);
+        let elapsed = now.abs_diff(self.start_ts) as u128;
+        let duration = self.end_ts.abs_diff(self.start_ts) as u128;
+
+        // start - (start - end) * elapsed / duration, kept over `duration` until the end
+        let drop = (self.start_receive - self.end_receive) as u128 * elapsed;
+        let price = (self.start_receive as u128 * duration - drop)
+            .checked_mul(amount as u128)
+            .ok_or(ErrorCode::MathOverflow)?;
+        let denominator = duration
+            .checked_mul(deposit as u128)
+            .filter(|d| *d > 0)
+            .ok_or(ErrorCode::MathOverflow)?;
+
+        Ok(u64::try_from(price.div_ceil(denominator)).map_err(|_| ErrorCode::MathOverflow)?)
+    }
+}
@@ -3,15 +3,19 @@ mod test {
 
     use {
         anchor_lang::{
-            prelude::msg, solana_program::program_pack::Pack, AccountDeserialize, InstructionData,
-            ToAccountMetas,
+            prelude::msg, solana_program::program_pack::Pack, AccountDeserialize, AccountSerialize,
+            AnchorSerialize, Event, InstructionData, Space, ToAccountMetas,
         },
         anchor_spl::{
             associated_token::{self},
             token::spl_token,
+            token_2022::{
+                self,
+                spl_token_2022::{self, extension::ExtensionType},
+            },
         },
-        litesvm::LiteSVM,
-        litesvm_token::{CreateAssociatedTokenAccount, CreateMint, MintTo},
+        litesvm::{types::TransactionResult, LiteSVM},
+        litesvm_token::{CloseAccount, CreateAssociatedTokenAccount, CreateMint, MintTo, Transfer},
         solana_account::Account,
         solana_address::Address,
         solana_instruction::Instruction,
@@ -24,6 +28,7 @@ mod test {
         std::{path::PathBuf, str::FromStr},
     };
 
+    use crate::error::ErrorCode;
     use anchor_lang::prelude::Pubkey;
     use solana_clock::Clock;
 
@@ -37,6 +42,325 @@ mod test {
         Pubkey::new_from_array(addr.to_bytes())
     }
 
+    fn convert_instruction(
+        ix: anchor_lang::solana_program::instruction::Instruction,
+    ) -> Instruction {
+        Instruction {
+            program_id: pubkey_to_addr(&ix.program_id),
+            accounts: ix
+                .accounts
+                .into_iter()
+                .map(|m| solana_instruction::AccountMeta {
+                    pubkey: pubkey_to_addr(&m.pubkey),
+                    is_signer: m.is_signer,
+                    is_writable: m.is_writable,
+                })
+                .collect(),
+            data: ix.data,
+        }
+    }
+
+    /// Creates a Token-2022 mint with 6 decimals, `payer` as mint authority and a
+    /// single extension set up by `init_extension` before the mint is initialised.
+    fn create_mint_2022(
+        program: &mut LiteSVM,
+        payer: &Keypair,
+        extension: ExtensionType,
+        init_extension: impl FnOnce(&Pubkey) -> anchor_lang::solana_program::instruction::Instruction,
+    ) -> Address {
+        let mint = Keypair::new();
+        let mint_pubkey = addr_to_pubkey(&mint.pubkey());
+
+        let space =
+            ExtensionType::try_calculate_account_len::<spl_token_2022::state::Mint>(&[extension])
+                .unwrap();
+
+        let create_ix = solana_system_interface::instruction::create_account(
+            &payer.pubkey(),
+            &mint.pubkey(),
+            program.minimum_balance_for_rent_exemption(space),
+            space as u64,
+            &pubkey_to_addr(&token_2022::ID),
+        );
+        let extension_ix = convert_instruction(init_extension(&mint_pubkey));
+        let init_mint_ix = convert_instruction(
+            spl_token_2022::instruction::initialize_mint2(
+                &token_2022::ID,
+                &mint_pubkey,
+                &addr_to_pubkey(&payer.pubkey()),
+                None,
+                6,
+            )
+            .unwrap(),
+        );
+
+        let message = Message::new(
+            &[create_ix, extension_ix, init_mint_ix],
+            Some(&payer.pubkey()),
+        );
+        let transaction = Transaction::new(&[payer, &mint], message, program.latest_blockhash());
+        program.send_transaction(transaction).unwrap();
+
+        mint.pubkey()
+    }
+
+    /// Creates a Token-2022 mint charging `fee_bps` on every transfer.
+    fn create_transfer_fee_mint(program: &mut LiteSVM, payer: &Keypair, fee_bps: u16) -> Address {
+        let authority = addr_to_pubkey(&payer.pubkey());
+        create_mint_2022(program, payer, ExtensionType::TransferFeeConfig, |mint| {
+            spl_token_2022::extension::transfer_fee::instruction::initialize_transfer_fee_config(
+                &token_2022::ID,
+                mint,
+                Some(&authority),
+                Some(&authority),
+                fee_bps,
+                u64::MAX,
+            )
+            .unwrap()
+        })
+    }
+
+    /// Creates a Token-2022 mint whose transfers run the local test hook program,
+    /// and initialises the hook's ExtraAccountMetaList for it.
+    fn create_transfer_hook_mint(program: &mut LiteSVM, payer: &Keypair) -> Address {
+        let so_path = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+            .join("../../target/deploy/test_transfer_hook.so");
+        let program_data = std::fs::read(so_path).expect("Failed to read hook program SO file");
+        let _ = program.add_program(pubkey_to_addr(&test_transfer_hook::ID), &program_data);
+
+        let authority = addr_to_pubkey(&payer.pubkey());
+        let mint = create_mint_2022(program, payer, ExtensionType::TransferHook, |mint| {
+            spl_token_2022::extension::transfer_hook::instruction::initialize(
+                &token_2022::ID,
+                mint,
+                Some(authority),
+                Some(test_transfer_hook::ID),
+            )
+            .unwrap()
+        });
+
+        let init_ix = convert_instruction(anchor_lang::solana_program::instruction::Instruction {
+            program_id: test_transfer_hook::ID,
+            accounts: test_transfer_hook::accounts::InitializeExtraAccountMetaList {
+                payer: authority,
+                extra_account_meta_list: hook_extra_account_metas(&addr_to_pubkey(&mint)),
+                mint: addr_to_pubkey(&mint),
+                counter: hook_counter(),
+                system_program: anchor_lang::system_program::ID,
+            }
+            .to_account_metas(None),
+            data: test_transfer_hook::instruction::InitializeExtraAccountMetaList.data(),
+        });
+
+        let message = Message::new(&[init_ix], Some(&payer.pubkey()));
+        let transaction = Transaction::new(&[payer], message, program.latest_blockhash());
+        program.send_transaction(transaction).unwrap();
+
+        mint
+    }
+
+    fn hook_extra_account_metas(mint: &Pubkey) -> Pubkey {
+        Pubkey::find_program_address(
+            &[b"extra-account-metas", mint.as_ref()],
+            &test_transfer_hook::ID,
+        )
+        .0
+    }
+
+    fn hook_counter() -> Pubkey {
+        Pubkey::find_program_address(&[b"counter"], &test_transfer_hook::ID).0
+    }
+
+    fn config_pda() -> (Pubkey, u8) {
+        Pubkey::find_program_address(&[b"config"], &PROGRAM_ID)
+    }
+
+    /// The wrapped SOL mint, written into the SVM if it is not already there.
+    fn native_mint(program: &mut LiteSVM) -> Address {
+        let mint = pubkey_to_addr(&spl_token::native_mint::ID);
+        if program.get_account(&mint).is_some() {
+            return mint;
+        }
+
+        let mut data = vec![0; spl_token::state::Mint::LEN];
+        spl_token::state::Mint {
+            decimals: spl_token::native_mint::DECIMALS,
+            is_initialized: true,
+            ..Default::default()
+        }
+        .pack_into_slice(&mut data);
+
+        program
+            .set_account(
+                mint,
+                Account {
+                    lamports: program.minimum_balance_for_rent_exemption(data.len()),
+                    data,
+                    owner: pubkey_to_addr(&spl_token::ID),
+                    executable: false,
+                    rent_epoch: 0,
+                },
+            )
+            .unwrap();
+
+        mint
+    }
+
+    /// Writes a mock Pyth `PriceUpdateV2` account quoting `price * 10^exponent`.
+    fn set_price(
+        program: &mut LiteSVM,
+        feed: Pubkey,
+        feed_id: [u8; 32],
+        price: i64,
+        conf: u64,
+        exponent: i32,
+        publish_time: i64,
+    ) {
+        use crate::oracle::{PriceFeedMessage, PriceUpdateV2, VerificationLevel};
+
+        let mut data = crate::oracle::PRICE_UPDATE_V2_DISCRIMINATOR.to_vec();
+        PriceUpdateV2 {
+            write_authority: Pubkey::default(),
+            verification_level: VerificationLevel::Full,
+            price_message: PriceFeedMessage {
+                feed_id,
+                price,
+                conf,
+                exponent,
+                publish_time,
+                prev_publish_time: publish_time,
+                ema_price: price,
+                ema_conf: conf,
+            },
+            posted_slot: 0,
+        }
+        .serialize(&mut data)
+        .unwrap();
+
+        program
+            .set_account(
+                pubkey_to_addr(&feed),
+                Account {
+                    lamports: program.minimum_balance_for_rent_exemption(data.len()),
+                    data,
+                    owner: pubkey_to_addr(&crate::oracle::PYTH_RECEIVER_PROGRAM_ID),
+                    executable: false,
+                    rent_epoch: 0,
+                },
+            )
+            .unwrap();
+    }
+
+    /// Writes the protocol config straight into the SVM, since `initialize_config`
+    /// needs the program to be deployed under the upgradeable loader.
+    fn set_config(
+        program: &mut LiteSVM,
+        admin: Pubkey,
+        treasury: Pubkey,
+        fee_bps: u16,
+        fee_on_mint_a: bool,
+    ) {
+        let (config, bump) = config_pda();
+
+        let mut data = Vec::with_capacity(8 + crate::state::Config::INIT_SPACE);
+        crate::state::Config {
+            admin,
+            treasury,
+            fee_bps,
+            fee_on_mint_a,
+            paused: 0,
+            bump,
+        }
+        .try_serialize(&mut data)
+        .unwrap();
+
+        program
+            .set_account(
+                pubkey_to_addr(&config),
+                Account {
+                    lamports: program.minimum_balance_for_rent_exemption(data.len()),
+                    data,
+                    owner: pubkey_to_addr(&PROGRAM_ID),
+                    executable: false,
+                    rent_epoch: 0,
+                },
+            )
+            .unwrap();
+    }
+
+    /// Redeploys the escrow under the upgradeable loader with `authority` as its
+    /// upgrade authority, returning the ProgramData address. The loader state is
+    /// bincode: a u32 variant tag followed by the variant's fields.
+    fn deploy_upgradeable(program: &mut LiteSVM, authority: Pubkey) -> Pubkey {
+        let loader = anchor_lang::solana_program::bpf_loader_upgradeable::ID;
+        let (program_data, _) = Pubkey::find_program_address(&[PROGRAM_ID.as_ref()], &loader);
+
+        let so_path =
+            PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("../../target/deploy/anchor_escrow.so");
+        let mut data = vec![3, 0, 0, 0];
+        data.extend(0u64.to_le_bytes());
+        data.push(1);
+        data.extend(authority.to_bytes());
+        data.extend(std::fs::read(so_path).expect("Failed to read program SO file"));
+        program
+            .set_account(
+                pubkey_to_addr(&program_data),
+                Account {
+                    lamports: program.minimum_balance_for_rent_exemption(data.len()),
+                    data,
+                    owner: pubkey_to_addr(&loader),
+                    executable: false,
+                    rent_epoch: 0,
+                },
+            )
+            .unwrap();
+
+        let mut data = vec![2, 0, 0, 0];
+        data.extend(program_data.to_bytes());
+        program
+            .set_account(
+                pubkey_to_addr(&PROGRAM_ID),
+                Account {
+                    lamports: program.minimum_balance_for_rent_exemption(data.len()),
+                    data,
+                    owner: pubkey_to_addr(&loader),
+                    executable: true,
+                    rent_epoch: 0,
+                },
+            )
+            .unwrap();
+
+        program_data
+    }
+
+    fn to_instruction(
+        accounts: Vec<anchor_lang::prelude::AccountMeta>,
+        data: Vec<u8>,
+    ) -> Instruction {
+        Instruction {
+            program_id: pubkey_to_addr(&PROGRAM_ID),
+            accounts: accounts
+                .into_iter()
+                .map(|m| solana_instruction::AccountMeta {
+                    pubkey: pubkey_to_addr(&m.pubkey),
+                    is_signer: m.is_signer,
+                    is_writable: m.is_writable,
+                })
+                .collect(),
+            data,
+        }
+    }
+
+    fn token_amount(program: &LiteSVM, account: &Address) -> u64 {
+        let account = program.get_account(account).unwrap();
+        spl_token_2022::extension::StateWithExtensions::<spl_token_2022::state::Account>::unpack(
+            &account.data,
+        )
+        .unwrap()
+        .base
+        .amount
+    }
+
     fn setup() -> (LiteSVM, Keypair) {
         let mut program = LiteSVM::new();
         let payer = Keypair::new();
@@ -77,6 +401,391 @@ mod test {
         (program, payer)
     }
 
+    /// Decodes every `E` emitted with `emit!` from a transaction's logs.
+    fn events<E: Event>(logs: &[String]) -> Vec<E> {
+        use anchor_lang::__private::base64::{engine::general_purpose::STANDARD, Engine};
+
+        logs.iter()
+            .filter_map(|log| log.strip_prefix("Program data: "))
+            .filter_map(|data| STANDARD.decode(data).ok())
+            .filter(|data| data.starts_with(E::DISCRIMINATOR))
+            .map(|data| E::deserialize(&mut &data[E::DISCRIMINATOR.len()..]).unwrap())
+            .collect()
+    }
+
+    /// Checks the transaction failed with `code`, either one of the program's
+    /// errors or one of Anchor's own constraint errors.
+    fn assert_error(result: TransactionResult, code: impl std::fmt::Debug) {
+        let failed = result.expect_err("Transaction should have failed");
+        let expected = format!("Error Code: {code:?}.");
+        assert!(
+            failed.meta.logs.iter().any(|log| log.contains(&expected)),
+            "Expected {code:?} in logs: {:#?}",
+            failed.meta.logs
+        );
+    }
+
+    /// A maker with mint_a inventory and a taker with mint_b inventory, both with
+    /// ATAs for each mint, plus the escrow and vault addresses for seed 123.
+    struct Fixture {
+        program: LiteSVM,
+        maker: Keypair,
+        taker: Keypair,
+        mint_a: Address,
+        mint_b: Address,
+        maker_ata_a: Address,
+        maker_ata_b: Address,
+        taker_ata_a: Address,
+        taker_ata_b: Address,
+        treasury: Pubkey,
+        treasury_ata_a: Address,
+        treasury_ata_b: Address,
+        escrow: Pubkey,
+        vault: Pubkey,
+        seed: u64,
+        token_program_a: Pubkey,
+        token_program_b: Pubkey,
+        /// Appended to every make/take, e.g. the accounts a transfer hook needs.
+        extra_accounts: Vec<anchor_lang::prelude::AccountMeta>,
+    }
+
+    // Helpers hand back LiteSVM's own `TransactionResult` so tests can inspect
+    // logs and metadata on either outcome; boxing it would only add noise.
+    #[allow(clippy::result_large_err)]
+    impl Fixture {
+        fn new() -> Self {
+            Self::with_token_programs(spl_token::ID, spl_token::ID)
+        }
+
+        fn with_token_programs(token_program_a: Pubkey, token_program_b: Pubkey) -> Self {
+            let (mut program, maker) = setup();
+
+            let mint_a = CreateMint::new(&mut program, &maker)
+                .token_program_id(&pubkey_to_addr(&token_program_a))
+                .decimals(6)
+                .authority(&maker.pubkey())
+                .send()
+                .unwrap();
+            let mint_b = CreateMint::new(&mut program, &maker)
+                .token_program_id(&pubkey_to_addr(&token_program_b))
+                .decimals(6)
+                .authority(&maker.pubkey())
+                .send()
+                .unwrap();
+
+            Self::with_mints(
+                program,
+                maker,
+                (mint_a, token_program_a),
+                (mint_b, token_program_b),
+            )
+        }
+
+        /// Like `new`, with wrapped SOL on the chosen legs.
+        fn with_native(native_a: bool, native_b: bool) -> Self {
+            let (mut program, maker) = setup();
+
+            let mut mint = |native: bool| {
+                if native {
+                    return native_mint(&mut program);
+                }
+                CreateMint::new(&mut program, &maker)
+                    .decimals(6)
+                    .authority(&maker.pubkey())
+                    .send()
+                    .unwrap()
+            };
+            let mint_a = mint(native_a);
+            let mint_b = mint(native_b);
+
+            Self::with_mints(
+                program,
+                maker,
+                (mint_a, spl_token::ID),
+                (mint_b, spl_token::ID),
+            )
+        }
+
+        fn with_mints(
+            mut program: LiteSVM,
+            maker: Keypair,
+            (mint_a, token_program_a): (Address, Pubkey),
+            (mint_b, token_program_b): (Address, Pubkey),
+        ) -> Self {
+            let token_program_a_addr = pubkey_to_addr(&token_program_a);
+            let token_program_b_addr = pubkey_to_addr(&token_program_b);
+            let taker = Keypair::new();
+
+            program
+                .airdrop(&taker.pubkey(), 10 * LAMPORTS_PER_SOL)
+                .expect("Failed to airdrop SOL to taker");
+
+            let maker_ata_a = CreateAssociatedTokenAccount::new(&mut program, &maker, &mint_a)
+                .owner(&maker.pubkey())
+                .token_program_id(&token_program_a_addr)
+                .send()
+                .unwrap();
+            let maker_ata_b = CreateAssociatedTokenAccount::new(&mut program, &maker, &mint_b)
+                .owner(&maker.pubkey())
+                .token_program_id(&token_program_b_addr)
+                .send()
+                .unwrap();
+            let taker_ata_a = CreateAssociatedTokenAccount::new(&mut program, &maker, &mint_a)
+                .owner(&taker.pubkey())
+                .token_program_id(&token_program_a_addr)
+                .send()
+                .unwrap();
+            let taker_ata_b = CreateAssociatedTokenAccount::new(&mut program, &maker, &mint_b)
+                .owner(&taker.pubkey())
+                .token_program_id(&token_program_b_addr)
+                .send()
+                .unwrap();
+
+            let treasury = addr_to_pubkey(&Keypair::new().pubkey());
+            let treasury_ata_a = CreateAssociatedTokenAccount::new(&mut program, &maker, &mint_a)
+                .owner(&pubkey_to_addr(&treasury))
+                .token_program_id(&token_program_a_addr)
+                .send()
+                .unwrap();
+            let treasury_ata_b = CreateAssociatedTokenAccount::new(&mut program, &maker, &mint_b)
+                .owner(&pubkey_to_addr(&treasury))
+                .token_program_id(&token_program_b_addr)
+                .send()
+                .unwrap();
+            set_config(
+                &mut program,
+                addr_to_pubkey(&maker.pubkey()),
+                treasury,
+                0,
+                false,
+            );
+
+            // Native SOL legs are funded from the maker's and taker's own lamports
+            if !crate::utils::is_native(&addr_to_pubkey(&mint_a)) {
+                MintTo::new(&mut program, &maker, &mint_a, &maker_ata_a, 1000000000)
+                    .token_program_id(&token_program_a_addr)
+                    .send()
+                    .unwrap();
+            }
+            if !crate::utils::is_native(&addr_to_pubkey(&mint_b)) {
+                MintTo::new(&mut program, &maker, &mint_b, &taker_ata_b, 1000000000)
+                    .token_program_id(&token_program_b_addr)
+                    .send()
+                    .unwrap();
+            }
+
+            let seed = 123u64;
+            let escrow = Pubkey::find_program_address(
+                &[
+                    b"escrow",
+                    addr_to_pubkey(&maker.pubkey()).as_ref(),
+                    &seed.to_le_bytes(),
+                ],
+                &PROGRAM_ID,
+            )
+            .0;
+            let vault = associated_token::get_associated_token_address_with_program_id(
+                &escrow,
+                &addr_to_pubkey(&mint_a),
+                &token_program_a,
+            );
+
+            Self {
+                program,
+                maker,
+                taker,
+                mint_a,
+                mint_b,
+                maker_ata_a,
+                maker_ata_b,
+                taker_ata_a,
+                taker_ata_b,
+                treasury,
+                treasury_ata_a,
+                treasury_ata_b,
+                escrow,
+                vault,
+                seed,
+                token_program_a,
+                token_program_b,
+                extra_accounts: vec![],
+            }
+        }
+
+        fn send(&mut self, ix: Instruction, signer: &Keypair) -> TransactionResult {
+            let message = Message::new(&[ix], Some(&signer.pubkey()));
+            let transaction = Transaction::new(&[signer], message, self.program.latest_blockhash());
+            let result = self.program.send_transaction(transaction);
+            self.program.expire_blockhash();
+            result
+        }
+
+        fn warp_to(&mut self, unix_timestamp: i64) {
+            let mut clock: Clock = self.program.get_sysvar();
+            clock.unix_timestamp = unix_timestamp;
+            self.program.set_sysvar(&clock);
+        }
+
+        fn set_fee(&mut self, fee_bps: u16, fee_on_mint_a: bool) {
+            let admin = addr_to_pubkey(&self.maker.pubkey());
+            set_config(
+                &mut self.program,
+                admin,
+                self.treasury,
+                fee_bps,
+                fee_on_mint_a,
+            );
+        }
+
+        fn set_pause(&mut self, admin: &Keypair, paused: u8) -> TransactionResult {
+            let ix = to_instruction(
+                crate::accounts::UpdateConfig {
+                    admin: addr_to_pubkey(&admin.pubkey()),
+                    config: config_pda().0,
+                }
+                .to_account_metas(None),
+                crate::instruction::SetPause { paused }.data(),
+            );
+            self.send(ix, admin)
+        }
+
+        fn config_data(&self) -> crate::state::Config {
+            let account = self
+                .program
+                .get_account(&pubkey_to_addr(&config_pda().0))
+                .unwrap();
+            crate::state::Config::try_deserialize(&mut account.data.as_ref()).unwrap()
+        }
+
+        fn escrow_data(&self) -> crate::state::Escrow {
+            let account = self
+                .program
+                .get_account(&pubkey_to_addr(&self.escrow))
+                .unwrap();
+            crate::state::Escrow::try_deserialize(&mut account.data.as_ref()).unwrap()
+        }
+
+        /// Token accounts for each leg, which a native SOL leg leaves out.
+        fn leg_a(&self, account: Pubkey) -> Option<Pubkey> {
+            (!crate::utils::is_native(&addr_to_pubkey(&self.mint_a))).then_some(account)
+        }
+
+        fn leg_b(&self, account: Pubkey) -> Option<Pubkey> {
+            (!crate::utils::is_native(&addr_to_pubkey(&self.mint_b))).then_some(account)
+        }
+
+        fn make_accounts(&self) -> crate::accounts::Make {
+            crate::accounts::Make {
+                maker: addr_to_pubkey(&self.maker.pubkey()),
+                payer: addr_to_pubkey(&self.maker.pubkey()),
+                mint_a: addr_to_pubkey(&self.mint_a),
+                mint_b: addr_to_pubkey(&self.mint_b),
+                maker_ata_a: self.leg_a(addr_to_pubkey(&self.maker_ata_a)),
+                escrow: self.escrow,
+                vault: self.leg_a(self.vault),
+                config: config_pda().0,
+                associated_token_program: associated_token::spl_associated_token_account::ID,
+                token_program: self.token_program_a,
+                system_program: anchor_lang::system_program::ID,
+            }
+        }
+
+        fn take_accounts(&self) -> crate::accounts::Take {
+            crate::accounts::Take {
+                maker: addr_to_pubkey(&self.maker.pubkey()),
+                rent_payer: addr_to_pubkey(&self.maker.pubkey()),
+                taker: addr_to_pubkey(&self.taker.pubkey()),
+                mint_a: addr_to_pubkey(&self.mint_a),
+                mint_b: addr_to_pubkey(&self.mint_b),
+                taker_ata_a: self.leg_a(addr_to_pubkey(&self.taker_ata_a)),
+                taker_ata_b: self.leg_b(addr_to_pubkey(&self.taker_ata_b)),
+                maker_ata_a: self.leg_a(addr_to_pubkey(&self.maker_ata_a)),
+                maker_ata_b: self.leg_b(addr_to_pubkey(&self.maker_ata_b)),
+                escrow: self.escrow,
+                vault: self.leg_a(self.vault),
+                config: config_pda().0,
+                treasury_ata: if self.config_data().fee_on_mint_a {
+                    self.leg_a(addr_to_pubkey(&self.treasury_ata_a))
+                } else {
+                    self.leg_b(addr_to_pubkey(&self.treasury_ata_b))
+                },
+                treasury: Some(self.treasury),
+                price_feed: None,
+                associated_token_program: associated_token::spl_associated_token_account::ID,
+                token_program_a: self.token_program_a,
+                token_program_b: self.token_program_b,
+                system_program: anchor_lang::system_program::ID,
+                clock: anchor_lang::solana_program::sysvar::clock::ID,
+            }
+        }
+
+        fn crank_accounts(&self, keeper: &Keypair) -> crate::accounts::CrankRefund {
+            crate::accounts::CrankRefund {
+                keeper: addr_to_pubkey(&keeper.pubkey()),
+                maker: addr_to_pubkey(&self.maker.pubkey()),
+                rent_payer: addr_to_pubkey(&self.maker.pubkey()),
+                mint_a: addr_to_pubkey(&self.mint_a),
+                maker_ata_a: self.leg_a(addr_to_pubkey(&self.maker_ata_a)),
+                escrow: self.escrow,
+                vault: self.leg_a(self.vault),
+                associated_token_program: associated_token::spl_associated_token_account::ID,
+                token_program: self.token_program_a,
+                system_program: anchor_lang::system_program::ID,
+            }
+        }
+
+        fn make_args(&self, deposit: u64, receive: u64) -> crate::state::MakeTerms {
+            crate::state::MakeTerms {
+                deposit,
+                receive,
+                receive_is_net: false,
+                not_before: None,
+                not_after: None,
+                allowed_taker: None,
+                taker_root: None,
+                pricing: crate::state::Pricing::Fixed,
+            }
+        }
+
+        fn make_with(&mut self, terms: crate::state::MakeTerms) -> TransactionResult {
+            let mut accounts = self.make_accounts().to_account_metas(None);
+            accounts.extend(self.extra_accounts.iter().cloned());
+            let args = crate::instruction::Make {
+                seed: self.seed,
+                terms,
+            };
+            let ix = to_instruction(accounts, args.data());
+            let maker = self.maker.insecure_clone();
+            self.send(ix, &maker)
+        }
+
+        fn make(&mut self, deposit: u64, receive: u64) -> TransactionResult {
+            let terms = self.make_args(deposit, receive);
+            self.make_with(terms)
+        }
+
+        fn take_with(&mut self, args: crate::instruction::Take) -> TransactionResult {
+            let mut accounts = self.take_accounts().to_account_metas(None);
+            accounts.extend(self.extra_accounts.iter().cloned());
+            let ix = to_instruction(accounts, args.data());
+            let taker = self.taker.insecure_clone();
+            self.send(ix, &taker)
+        }
+
+        /// Takes `amount` at the escrow's current terms.
+        fn take(&mut self, amount: u64) -> TransactionResult {
+            let escrow_data = self.escrow_data();
+            self.take_with(crate::instruction::Take {
+                amount,
+                expected_deposit: escrow_data.remaining,
+                expected_receive: escrow_data.receive,
+                max_fee_bps: self.config_data().fee_bps,
+                payment_option: 0,
+                proof: vec![],
+            })
+        }
+    }
+
     #[test]
     fn test_make() {
         let (mut program, payer) = setup();
@@ -123,13 +832,17 @@ mod test {
             .send()
             .unwrap();
 
+        set_config(&mut program, maker_pubkey, maker_pubkey, 0, false);
+
         let anchor_accounts = crate::accounts::Make {
             maker: maker_pubkey,
+            payer: maker_pubkey,
             mint_a: addr_to_pubkey(&mint_a),
             mint_b: addr_to_pubkey(&mint_b),
-            maker_ata_a: addr_to_pubkey(&maker_ata_a),
+            maker_ata_a: Some(addr_to_pubkey(&maker_ata_a)),
             escrow,
-            vault,
+            vault: Some(vault),
+            config: config_pda().0,
             associated_token_program: asspciated_token_program,
             token_program,
             system_program,
@@ -147,9 +860,17 @@ mod test {
                 })
                 .collect(),
             data: crate::instruction::Make {
-                deposit: 10,
                 seed: 123u64,
-                receive: 10,
+                terms: crate::state::MakeTerms {
+                    deposit: 10,
+                    receive: 10,
+                    receive_is_net: false,
+                    not_before: None,
+                    not_after: None,
+                    allowed_taker: None,
+                    taker_root: None,
+                    pricing: crate::state::Pricing::Fixed,
+                },
             }
             .data(),
         };
@@ -165,6 +886,18 @@ mod test {
         msg!("CUs Consumed: {}", tx.compute_units_consumed);
         msg!("Tx Signature: {}", tx.signature);
 
+        let made = events::<crate::events::EscrowMade>(&tx.logs);
+        assert_eq!(made.len(), 1);
+        assert_eq!(made[0].escrow, escrow);
+        assert_eq!(made[0].maker, maker_pubkey);
+        assert_eq!(made[0].mint_a, addr_to_pubkey(&mint_a));
+        assert_eq!(made[0].mint_b, addr_to_pubkey(&mint_b));
+        assert_eq!(made[0].rent_payer, maker_pubkey);
+        assert_eq!(made[0].deposit, 10);
+        assert_eq!(made[0].receive, 10);
+        assert!(!made[0].receive_is_net);
+        assert_eq!(made[0].pricing, crate::state::Pricing::Fixed);
+
         let vault_account = program.get_account(&pubkey_to_addr(&vault)).unwrap();
         let vault_data = spl_token::state::Account::unpack(&vault_account.data).unwrap();
         assert_eq!(vault_data.amount, 10);
@@ -232,6 +965,19 @@ mod test {
             .send()
             .unwrap();
 
+        let treasury = Keypair::new();
+        let treasury_ata_b = CreateAssociatedTokenAccount::new(&mut program, &payer, &mint_b)
+            .owner(&treasury.pubkey())
+            .send()
+            .unwrap();
+        set_config(
+            &mut program,
+            addr_to_pubkey(&maker),
+            addr_to_pubkey(&treasury.pubkey()),
+            0,
+            false,
+        );
+
         MintTo::new(&mut program, &payer, &mint_a, &maker_ata_a, 1000000000)
             .send()
             .unwrap();
@@ -258,11 +1004,13 @@ mod test {
 
         let make_accounts = crate::accounts::Make {
             maker: maker_pubkey,
+            payer: maker_pubkey,
             mint_a: addr_to_pubkey(&mint_a),
             mint_b: addr_to_pubkey(&mint_b),
-            maker_ata_a: addr_to_pubkey(&maker_ata_a),
+            maker_ata_a: Some(addr_to_pubkey(&maker_ata_a)),
             escrow,
-            vault,
+            vault: Some(vault),
+            config: config_pda().0,
             associated_token_program: asspciated_token_program,
             token_program,
             system_program,
@@ -280,9 +1028,17 @@ mod test {
                 })
                 .collect(),
             data: crate::instruction::Make {
-                deposit: 10,
                 seed: 123u64,
-                receive: 10,
+                terms: crate::state::MakeTerms {
+                    deposit: 10,
+                    receive: 10,
+                    receive_is_net: false,
+                    not_before: None,
+                    not_after: None,
+                    allowed_taker: None,
+                    taker_root: None,
+                    pricing: crate::state::Pricing::Fixed,
+                },
             }
             .data(),
         };
@@ -302,16 +1058,23 @@ mod test {
 
         let anchor_accounts = crate::accounts::Take {
             maker: addr_to_pubkey(&maker),
+            rent_payer: addr_to_pubkey(&maker),
             taker: addr_to_pubkey(&taker.pubkey()),
             mint_a: addr_to_pubkey(&mint_a),
             mint_b: addr_to_pubkey(&mint_b),
-            taker_ata_a: addr_to_pubkey(&taker_ata_a),
-            taker_ata_b: addr_to_pubkey(&taker_ata_b),
-            maker_ata_b: addr_to_pubkey(&maker_ata_b),
+            taker_ata_a: Some(addr_to_pubkey(&taker_ata_a)),
+            taker_ata_b: Some(addr_to_pubkey(&taker_ata_b)),
+            maker_ata_a: Some(addr_to_pubkey(&maker_ata_a)),
+            maker_ata_b: Some(addr_to_pubkey(&maker_ata_b)),
             escrow,
-            vault,
+            vault: Some(vault),
+            config: config_pda().0,
+            treasury_ata: Some(addr_to_pubkey(&treasury_ata_b)),
+            treasury: Some(addr_to_pubkey(&treasury.pubkey())),
+            price_feed: None,
             associated_token_program: asspciated_token_program,
-            token_program,
+            token_program_a: token_program,
+            token_program_b: token_program,
             system_program,
             clock: anchor_lang::solana_program::sysvar::clock::ID,
         }
@@ -327,7 +1090,15 @@ mod test {
                     is_writable: m.is_writable,
                 })
                 .collect(),
-            data: crate::instruction::Take.data(),
+            data: crate::instruction::Take {
+                amount: 10,
+                expected_deposit: 10,
+                expected_receive: 10,
+                max_fee_bps: 0,
+                payment_option: 0,
+                proof: vec![],
+            }
+            .data(),
         };
 
         let message = Message::new(&[take_ix], Some(&taker.pubkey()));
@@ -341,6 +1112,15 @@ mod test {
         msg!("CUs Consumed: {}", tx.compute_units_consumed);
         msg!("Tx Signature: {}", tx.signature);
 
+        let taken = events::<crate::events::EscrowTaken>(&tx.logs);
+        assert_eq!(taken.len(), 1);
+        assert_eq!(taken[0].escrow, escrow);
+        assert_eq!(taken[0].taker, addr_to_pubkey(&taker.pubkey()));
+        assert_eq!(taken[0].amount, 10);
+        assert_eq!(taken[0].paid, 10);
+        assert_eq!(taken[0].fee, 0);
+        assert_eq!(taken[0].remaining, 0);
+
         let taker_ata_a_account = program.get_account(&taker_ata_a).unwrap();
         let taker_ata_a_data =
             spl_token::state::Account::unpack(&taker_ata_a_account.data).unwrap();
@@ -417,13 +1197,17 @@ mod test {
             .send()
             .unwrap();
 
+        set_config(&mut program, maker_pubkey, maker_pubkey, 0, false);
+
         let make_accounts = crate::accounts::Make {
             maker: maker_pubkey,
+            payer: maker_pubkey,
             mint_a: addr_to_pubkey(&mint_a),
             mint_b: addr_to_pubkey(&mint_b),
-            maker_ata_a: addr_to_pubkey(&maker_ata_a),
+            maker_ata_a: Some(addr_to_pubkey(&maker_ata_a)),
             escrow,
-            vault,
+            vault: Some(vault),
+            config: config_pda().0,
             associated_token_program: asspciated_token_program,
             token_program,
             system_program,
@@ -441,9 +1225,17 @@ mod test {
                 })
                 .collect(),
             data: crate::instruction::Make {
-                deposit: 10,
                 seed: 123u64,
-                receive: 10,
+                terms: crate::state::MakeTerms {
+                    deposit: 10,
+                    receive: 10,
+                    receive_is_net: false,
+                    not_before: None,
+                    not_after: None,
+                    allowed_taker: None,
+                    taker_root: None,
+                    pricing: crate::state::Pricing::Fixed,
+                },
             }
             .data(),
         };
@@ -457,10 +1249,11 @@ mod test {
 
         let refund_accounts = crate::accounts::Refund {
             maker: maker_pubkey,
+            rent_payer: maker_pubkey,
             mint_a: addr_to_pubkey(&mint_a),
-            maker_ata_a: addr_to_pubkey(&maker_ata_a),
+            maker_ata_a: Some(addr_to_pubkey(&maker_ata_a)),
             escrow,
-            vault,
+            vault: Some(vault),
             token_program,
             system_program,
         }
@@ -490,6 +1283,12 @@ mod test {
         msg!("CUs Consumed: {}", tx.compute_units_consumed);
         msg!("Tx Signature: {}", tx.signature);
 
+        let refunded = events::<crate::events::EscrowRefunded>(&tx.logs);
+        assert_eq!(refunded.len(), 1);
+        assert_eq!(refunded[0].escrow, escrow);
+        assert_eq!(refunded[0].amount, 10);
+        assert_eq!(refunded[0].keeper, None);
+
         let maker_ata_a_account = program
             .get_account(&pubkey_to_addr(&addr_to_pubkey(&maker_ata_a)))
             .unwrap();
@@ -505,4 +1304,1879 @@ mod test {
         let escrow_account = program.get_account(&pubkey_to_addr(&escrow));
         assert!(escrow_account.is_none(), "Escrow should be closed");
     }
+
+    #[test]
+    fn test_take_partial() {
+        let mut f = Fixture::new();
+        f.make(10, 25).unwrap();
+
+        // First fill: 4 of 10 costs ceil(25 * 4 / 10) = 10
+        let tx = f.take(4).unwrap();
+
+        let taken = events::<crate::events::EscrowTaken>(&tx.logs);
+        assert_eq!(taken[0].amount, 4);
+        assert_eq!(taken[0].paid, 10);
+        assert_eq!(taken[0].remaining, 6);
+
+        assert_eq!(token_amount(&f.program, &f.taker_ata_a), 4);
+        assert_eq!(token_amount(&f.program, &f.maker_ata_b), 10);
+        assert_eq!(token_amount(&f.program, &pubkey_to_addr(&f.vault)), 6);
+
+        let escrow_data = f.escrow_data();
+        assert_eq!(escrow_data.remaining, 6);
+        assert_eq!(escrow_data.receive, 15);
+
+        // Overfilling the remainder is rejected
+        assert_error(f.take(7), ErrorCode::InvalidFillAmount);
+
+        // Final fill pays what is left of receive and closes everything
+        f.take(6).unwrap();
+
+        assert_eq!(token_amount(&f.program, &f.taker_ata_a), 10);
+        assert_eq!(token_amount(&f.program, &f.maker_ata_b), 25);
+        assert_eq!(token_amount(&f.program, &f.taker_ata_b), 1000000000 - 25);
+
+        let vault_account = f.program.get_account(&pubkey_to_addr(&f.vault));
+        assert!(vault_account.is_none(), "Vault should be closed");
+
+        let escrow_account = f.program.get_account(&pubkey_to_addr(&f.escrow));
+        assert!(escrow_account.is_none(), "Escrow should be closed");
+    }
+
+    #[test]
+    fn test_take_window() {
+        let mut f = Fixture::new();
+        let now = f.program.get_sysvar::<Clock>().unix_timestamp;
+
+        assert_error(
+            f.make_with(crate::state::MakeTerms {
+                not_before: Some(now + 100),
+                not_after: Some(now + 50),
+                ..f.make_args(10, 10)
+            }),
+            ErrorCode::InvalidTakeWindow,
+        );
+
+        f.make_with(crate::state::MakeTerms {
+            not_before: Some(now + 100),
+            not_after: Some(now + 200),
+            ..f.make_args(10, 10)
+        })
+        .unwrap();
+
+        assert_error(f.take(5), ErrorCode::TooEarlyToTake);
+
+        f.warp_to(now + 100);
+        f.take(5).unwrap();
+
+        f.warp_to(now + 201);
+        assert_error(f.take(5), ErrorCode::EscrowExpired);
+    }
+
+    #[test]
+    fn test_crank_refund() {
+        let mut f = Fixture::new();
+        let now = f.program.get_sysvar::<Clock>().unix_timestamp;
+
+        f.make_with(crate::state::MakeTerms {
+            not_after: Some(now + 100),
+            ..f.make_args(10, 10)
+        })
+        .unwrap();
+
+        let keeper = Keypair::new();
+        f.program
+            .airdrop(&keeper.pubkey(), LAMPORTS_PER_SOL)
+            .expect("Failed to airdrop SOL to keeper");
+
+        let crank_ix = to_instruction(
+            f.crank_accounts(&keeper).to_account_metas(None),
+            crate::instruction::CrankRefund.data(),
+        );
+
+        assert_error(
+            f.send(crank_ix.clone(), &keeper),
+            ErrorCode::EscrowNotExpired,
+        );
+
+        f.warp_to(now + 101);
+
+        let keeper_before = f.program.get_balance(&keeper.pubkey()).unwrap();
+        let tx = f.send(crank_ix, &keeper).unwrap();
+        let keeper_after = f.program.get_balance(&keeper.pubkey()).unwrap();
+
+        assert_eq!(
+            keeper_after,
+            keeper_before + crate::instructions::CRANK_BOUNTY_LAMPORTS - 5000
+        );
+        assert_eq!(token_amount(&f.program, &f.maker_ata_a), 1000000000);
+
+        let refunded = events::<crate::events::EscrowRefunded>(&tx.logs);
+        assert_eq!(refunded[0].amount, 10);
+        assert_eq!(refunded[0].keeper, Some(addr_to_pubkey(&keeper.pubkey())));
+        assert_eq!(
+            refunded[0].bounty,
+            crate::instructions::CRANK_BOUNTY_LAMPORTS
+        );
+
+        let vault_account = f.program.get_account(&pubkey_to_addr(&f.vault));
+        assert!(vault_account.is_none(), "Vault should be closed");
+
+        let escrow_account = f.program.get_account(&pubkey_to_addr(&f.escrow));
+        assert!(escrow_account.is_none(), "Escrow should be closed");
+    }
+
+    #[test]
+    fn test_crank_refund_closed_maker_ata() {
+        let mut f = Fixture::new();
+        let now = f.program.get_sysvar::<Clock>().unix_timestamp;
+
+        f.make_with(crate::state::MakeTerms {
+            not_after: Some(now + 100),
+            ..f.make_args(10, 10)
+        })
+        .unwrap();
+
+        // The maker walks away and closes their mint_a account
+        let maker = f.maker.insecure_clone();
+        let rest = token_amount(&f.program, &f.maker_ata_a);
+        Transfer::new(&mut f.program, &maker, &f.mint_a, &f.taker_ata_a, rest)
+            .send()
+            .unwrap();
+        CloseAccount::new(&mut f.program, &maker, &f.maker_ata_a, &maker.pubkey())
+            .send()
+            .unwrap();
+        assert!(f.program.get_account(&f.maker_ata_a).is_none());
+
+        let keeper = Keypair::new();
+        f.program
+            .airdrop(&keeper.pubkey(), LAMPORTS_PER_SOL)
+            .expect("Failed to airdrop SOL to keeper");
+
+        f.warp_to(now + 101);
+
+        let crank_ix = to_instruction(
+            f.crank_accounts(&keeper).to_account_metas(None),
+            crate::instruction::CrankRefund.data(),
+        );
+        f.send(crank_ix, &keeper).unwrap();
+
+        // The keeper recreated the account to return the deposit
+        assert_eq!(token_amount(&f.program, &f.maker_ata_a), 10);
+        assert!(f.program.get_account(&pubkey_to_addr(&f.escrow)).is_none());
+    }
+
+    #[test]
+    fn test_take_private() {
+        let mut f = Fixture::new();
+        let desk = Keypair::new();
+
+        f.make_with(crate::state::MakeTerms {
+            allowed_taker: Some(addr_to_pubkey(&desk.pubkey())),
+            ..f.make_args(10, 10)
+        })
+        .unwrap();
+
+        assert_error(f.take(10), ErrorCode::TakerNotAllowed);
+
+        f.taker = desk;
+        f.program
+            .airdrop(&f.taker.pubkey(), LAMPORTS_PER_SOL)
+            .expect("Failed to airdrop SOL to taker");
+        f.taker_ata_a = CreateAssociatedTokenAccount::new(&mut f.program, &f.maker, &f.mint_a)
+            .owner(&f.taker.pubkey())
+            .send()
+            .unwrap();
+        f.taker_ata_b = CreateAssociatedTokenAccount::new(&mut f.program, &f.maker, &f.mint_b)
+            .owner(&f.taker.pubkey())
+            .send()
+            .unwrap();
+        MintTo::new(&mut f.program, &f.maker, &f.mint_b, &f.taker_ata_b, 10)
+            .send()
+            .unwrap();
+
+        f.take(10).unwrap();
+
+        assert_eq!(token_amount(&f.program, &f.taker_ata_a), 10);
+        assert_eq!(token_amount(&f.program, &f.maker_ata_b), 10);
+    }
+
+    #[test]
+    fn test_take_token_2022() {
+        let mut f = Fixture::with_token_programs(token_2022::ID, spl_token::ID);
+        f.make(10, 20).unwrap();
+
+        f.take(10).unwrap();
+
+        assert_eq!(token_amount(&f.program, &f.taker_ata_a), 10);
+        assert_eq!(token_amount(&f.program, &f.maker_ata_b), 20);
+
+        let vault_account = f.program.get_account(&pubkey_to_addr(&f.vault));
+        assert!(vault_account.is_none(), "Vault should be closed");
+    }
+
+    #[test]
+    fn test_take_transfer_fee() {
+        let (mut program, maker) = setup();
+
+        // 1% fee on both legs
+        let mint_a = create_transfer_fee_mint(&mut program, &maker, 100);
+        let mint_b = create_transfer_fee_mint(&mut program, &maker, 100);
+
+        let mut f = Fixture::with_mints(
+            program,
+            maker,
+            (mint_a, token_2022::ID),
+            (mint_b, token_2022::ID),
+        );
+
+        f.make_with(crate::state::MakeTerms {
+            receive_is_net: true,
+            ..f.make_args(1000, 1000)
+        })
+        .unwrap();
+
+        // The escrow only offers what reached the vault
+        let escrow_data = f.escrow_data();
+        assert_eq!(escrow_data.remaining, 990);
+        assert_eq!(token_amount(&f.program, &pubkey_to_addr(&f.vault)), 990);
+
+        // The taker grosses up the payment so the maker nets exactly `receive`,
+        // and must expect to pay the grossed-up amount
+        assert_error(
+            f.take_with(crate::instruction::Take {
+                amount: 990,
+                expected_deposit: 990,
+                expected_receive: 1000,
+                max_fee_bps: 0,
+                payment_option: 0,
+                proof: vec![],
+            }),
+            ErrorCode::TermsMismatch,
+        );
+        f.take_with(crate::instruction::Take {
+            amount: 990,
+            expected_deposit: 990,
+            expected_receive: 1011,
+            max_fee_bps: 0,
+            payment_option: 0,
+            proof: vec![],
+        })
+        .unwrap();
+
+        assert_eq!(token_amount(&f.program, &f.maker_ata_b), 1000);
+        assert_eq!(1000000000 - token_amount(&f.program, &f.taker_ata_b), 1011);
+
+        // Fees withheld in the vault are harvested so it can still be closed
+        let vault_account = f.program.get_account(&pubkey_to_addr(&f.vault));
+        assert!(vault_account.is_none(), "Vault should be closed");
+    }
+
+    #[test]
+    fn test_take_transfer_hook() {
+        let (mut program, maker) = setup();
+
+        let mint_a = create_transfer_hook_mint(&mut program, &maker);
+        let mint_b = CreateMint::new(&mut program, &maker)
+            .decimals(6)
+            .authority(&maker.pubkey())
+            .send()
+            .unwrap();
+
+        let mut f = Fixture::with_mints(
+            program,
+            maker,
+            (mint_a, token_2022::ID),
+            (mint_b, spl_token::ID),
+        );
+        f.extra_accounts = vec![
+            anchor_lang::prelude::AccountMeta::new_readonly(
+                hook_extra_account_metas(&addr_to_pubkey(&mint_a)),
+                false,
+            ),
+            anchor_lang::prelude::AccountMeta::new(hook_counter(), false),
+            anchor_lang::prelude::AccountMeta::new_readonly(test_transfer_hook::ID, false),
+        ];
+
+        f.make(10, 10).unwrap();
+        f.take(10).unwrap();
+
+        assert_eq!(token_amount(&f.program, &f.taker_ata_a), 10);
+        assert_eq!(token_amount(&f.program, &f.maker_ata_b), 10);
+
+        // The hook ran for both the deposit and the release
+        let counter_account = f
+            .program
+            .get_account(&pubkey_to_addr(&hook_counter()))
+            .unwrap();
+        let counter =
+            test_transfer_hook::Counter::try_deserialize(&mut counter_account.data.as_ref())
+                .unwrap();
+        assert_eq!(counter.transfers, 2);
+    }
+
+    #[test]
+    fn test_make_mint_risk() {
+        let (mut program, maker) = setup();
+
+        let delegate = addr_to_pubkey(&Keypair::new().pubkey());
+        let mint_a = create_mint_2022(
+            &mut program,
+            &maker,
+            ExtensionType::PermanentDelegate,
+            |mint| {
+                spl_token_2022::instruction::initialize_permanent_delegate(
+                    &token_2022::ID,
+                    mint,
+                    &delegate,
+                )
+                .unwrap()
+            },
+        );
+        let mint_b = CreateMint::new(&mut program, &maker)
+            .decimals(6)
+            .authority(&maker.pubkey())
+            .send()
+            .unwrap();
+
+        let mut f = Fixture::with_mints(
+            program,
+            maker,
+            (mint_a, token_2022::ID),
+            (mint_b, spl_token::ID),
+        );
+        f.make(10, 10).unwrap();
+
+        let escrow_data = f.escrow_data();
+        assert_eq!(
+            escrow_data.mint_a_risk,
+            crate::state::MINT_RISK_PERMANENT_DELEGATE
+        );
+        assert_eq!(escrow_data.mint_b_risk, 0);
+    }
+
+    #[test]
+    fn test_make_empty_deposit() {
+        let mut f = Fixture::new();
+        assert_error(f.make(0, 10), ErrorCode::EmptyDeposit);
+
+        // The 1% fee on a single token rounds up and swallows the whole deposit
+        let (mut program, maker) = setup();
+        let mint_a = create_transfer_fee_mint(&mut program, &maker, 100);
+        let mint_b = create_transfer_fee_mint(&mut program, &maker, 100);
+
+        let mut f = Fixture::with_mints(
+            program,
+            maker,
+            (mint_a, token_2022::ID),
+            (mint_b, token_2022::ID),
+        );
+        assert_error(f.make(1, 10), ErrorCode::EmptyDeposit);
+    }
+
+    #[test]
+    fn test_make_non_transferable() {
+        let (mut program, maker) = setup();
+
+        let mint_a = create_mint_2022(
+            &mut program,
+            &maker,
+            ExtensionType::NonTransferable,
+            |mint| {
+                spl_token_2022::instruction::initialize_non_transferable_mint(&token_2022::ID, mint)
+                    .unwrap()
+            },
+        );
+        let mint_b = CreateMint::new(&mut program, &maker)
+            .decimals(6)
+            .authority(&maker.pubkey())
+            .send()
+            .unwrap();
+
+        let mut f = Fixture::with_mints(
+            program,
+            maker,
+            (mint_a, token_2022::ID),
+            (mint_b, spl_token::ID),
+        );
+
+        assert_error(f.make(10, 10), ErrorCode::NonTransferableMint);
+    }
+
+    #[test]
+    fn test_amend() {
+        let mut f = Fixture::new();
+        let now = f.program.get_sysvar::<Clock>().unix_timestamp;
+
+        f.make(10, 10).unwrap();
+
+        let amend_data = crate::instruction::Amend {
+            receive: 30,
+            not_before: None,
+            not_after: Some(now + 100),
+        }
+        .data();
+
+        // Only the maker can amend
+        let amend_ix = to_instruction(
+            crate::accounts::Amend {
+                maker: addr_to_pubkey(&f.taker.pubkey()),
+                escrow: f.escrow,
+                config: config_pda().0,
+            }
+            .to_account_metas(None),
+            amend_data.clone(),
+        );
+        let taker = f.taker.insecure_clone();
+        assert!(f.send(amend_ix, &taker).is_err());
+
+        let amend_ix = to_instruction(
+            crate::accounts::Amend {
+                maker: addr_to_pubkey(&f.maker.pubkey()),
+                escrow: f.escrow,
+                config: config_pda().0,
+            }
+            .to_account_metas(None),
+            amend_data,
+        );
+        let maker = f.maker.insecure_clone();
+        let tx = f.send(amend_ix, &maker).unwrap();
+
+        let amended = events::<crate::events::EscrowAmended>(&tx.logs);
+        assert_eq!(amended[0].old_receive, 10);
+        assert_eq!(amended[0].new_receive, 30);
+
+        let escrow_data = f.escrow_data();
+        assert_eq!(escrow_data.receive, 30);
+        assert_eq!(escrow_data.not_after, Some(now + 100));
+
+        f.take(10).unwrap();
+        assert_eq!(token_amount(&f.program, &f.maker_ata_b), 30);
+    }
+
+    #[test]
+    fn test_take_terms_mismatch() {
+        let mut f = Fixture::new();
+        f.make(10, 10).unwrap();
+
+        // Quoted at 10 for 10, but the maker reprices before the take lands
+        let amend_ix = to_instruction(
+            crate::accounts::Amend {
+                maker: addr_to_pubkey(&f.maker.pubkey()),
+                escrow: f.escrow,
+                config: config_pda().0,
+            }
+            .to_account_metas(None),
+            crate::instruction::Amend {
+                receive: 50,
+                not_before: None,
+                not_after: None,
+            }
+            .data(),
+        );
+        let maker = f.maker.insecure_clone();
+        f.send(amend_ix, &maker).unwrap();
+
+        assert_error(
+            f.take_with(crate::instruction::Take {
+                amount: 10,
+                expected_deposit: 10,
+                expected_receive: 10,
+                max_fee_bps: 0,
+                payment_option: 0,
+                proof: vec![],
+            }),
+            ErrorCode::TermsMismatch,
+        );
+        assert_eq!(token_amount(&f.program, &f.maker_ata_b), 0);
+    }
+
+    #[test]
+    fn test_top_up_and_withdraw_partial() {
+        let mut f = Fixture::new();
+        f.make(10, 20).unwrap();
+
+        let maker = f.maker.insecure_clone();
+
+        let top_up_ix = to_instruction(
+            crate::accounts::TopUp {
+                maker: addr_to_pubkey(&f.maker.pubkey()),
+                mint_a: addr_to_pubkey(&f.mint_a),
+                maker_ata_a: f.leg_a(addr_to_pubkey(&f.maker_ata_a)),
+                escrow: f.escrow,
+                vault: f.leg_a(f.vault),
+                config: config_pda().0,
+                token_program: f.token_program_a,
+                system_program: anchor_lang::system_program::ID,
+            }
+            .to_account_metas(None),
+            crate::instruction::TopUp {
+                amount: 10,
+                scale_receive: true,
+            }
+            .data(),
+        );
+        let tx = f.send(top_up_ix, &maker).unwrap();
+
+        let topped_up = events::<crate::events::EscrowToppedUp>(&tx.logs);
+        assert_eq!(topped_up[0].amount, 10);
+        assert_eq!(topped_up[0].receive, 40);
+
+        let escrow_data = f.escrow_data();
+        assert_eq!(escrow_data.remaining, 20);
+        assert_eq!(escrow_data.receive, 40);
+
+        let withdraw_accounts = crate::accounts::WithdrawPartial {
+            maker: addr_to_pubkey(&f.maker.pubkey()),
+            mint_a: addr_to_pubkey(&f.mint_a),
+            maker_ata_a: f.leg_a(addr_to_pubkey(&f.maker_ata_a)),
+            escrow: f.escrow,
+            vault: f.leg_a(f.vault),
+            token_program: f.token_program_a,
+        };
+
+        // Withdrawing everything is what refund is for
+        let withdraw_ix = to_instruction(
+            withdraw_accounts.to_account_metas(None),
+            crate::instruction::WithdrawPartial { amount: 20 }.data(),
+        );
+        assert_error(
+            f.send(withdraw_ix, &maker),
+            ErrorCode::InvalidWithdrawAmount,
+        );
+
+        let withdraw_ix = to_instruction(
+            withdraw_accounts.to_account_metas(None),
+            crate::instruction::WithdrawPartial { amount: 5 }.data(),
+        );
+        let tx = f.send(withdraw_ix, &maker).unwrap();
+
+        let withdrawn = events::<crate::events::EscrowWithdrawn>(&tx.logs);
+        assert_eq!(withdrawn[0].remaining, f.escrow_data().remaining);
+
+        let escrow_data = f.escrow_data();
+        assert_eq!(escrow_data.remaining, 15);
+        assert_eq!(escrow_data.receive, 30);
+        assert_eq!(token_amount(&f.program, &pubkey_to_addr(&f.vault)), 15);
+        assert_eq!(token_amount(&f.program, &f.maker_ata_a), 1000000000 - 15);
+    }
+
+    #[test]
+    fn test_sweep_surplus() {
+        let mut f = Fixture::new();
+        f.make(10, 10).unwrap();
+
+        // Someone sends tokens straight to the vault
+        MintTo::new(
+            &mut f.program,
+            &f.maker,
+            &f.mint_a,
+            &pubkey_to_addr(&f.vault),
+            5,
+        )
+        .send()
+        .unwrap();
+
+        // Takers still get exactly what was deposited
+        f.take(4).unwrap();
+        assert_eq!(token_amount(&f.program, &f.taker_ata_a), 4);
+
+        let escrow_data = f.escrow_data();
+        assert_eq!(escrow_data.deposit, 10);
+        assert_eq!(escrow_data.remaining, 6);
+
+        let sweep_ix = to_instruction(
+            crate::accounts::Sweep {
+                maker: addr_to_pubkey(&f.maker.pubkey()),
+                mint_a: addr_to_pubkey(&f.mint_a),
+                maker_ata_a: f.leg_a(addr_to_pubkey(&f.maker_ata_a)),
+                escrow: f.escrow,
+                vault: f.leg_a(f.vault),
+                token_program: f.token_program_a,
+            }
+            .to_account_metas(None),
+            crate::instruction::Sweep.data(),
+        );
+        let maker = f.maker.insecure_clone();
+        let tx = f.send(sweep_ix, &maker).unwrap();
+
+        let swept = events::<crate::events::SurplusSwept>(&tx.logs);
+        assert_eq!(swept[0].amount, 5);
+
+        assert_eq!(
+            token_amount(&f.program, &f.maker_ata_a),
+            1000000000 - 10 + 5
+        );
+        assert_eq!(token_amount(&f.program, &pubkey_to_addr(&f.vault)), 6);
+
+        f.take(6).unwrap();
+        assert_eq!(token_amount(&f.program, &f.taker_ata_a), 10);
+
+        let escrow_account = f.program.get_account(&pubkey_to_addr(&f.escrow));
+        assert!(escrow_account.is_none(), "Escrow should be closed");
+    }
+
+    #[test]
+    fn test_take_final_fill_returns_surplus() {
+        let mut f = Fixture::new();
+        f.make(10, 10).unwrap();
+
+        MintTo::new(
+            &mut f.program,
+            &f.maker,
+            &f.mint_a,
+            &pubkey_to_addr(&f.vault),
+            5,
+        )
+        .send()
+        .unwrap();
+
+        // The last fill cannot leave the escrow open over a few stray tokens
+        f.take(10).unwrap();
+        assert_eq!(token_amount(&f.program, &f.taker_ata_a), 10);
+        assert_eq!(
+            token_amount(&f.program, &f.maker_ata_a),
+            1000000000 - 10 + 5
+        );
+
+        let vault_account = f.program.get_account(&pubkey_to_addr(&f.vault));
+        assert!(vault_account.is_none(), "Vault should be closed");
+        let escrow_account = f.program.get_account(&pubkey_to_addr(&f.escrow));
+        assert!(escrow_account.is_none(), "Escrow should be closed");
+    }
+
+    #[test]
+    fn test_take_protocol_fee() {
+        let mut f = Fixture::new();
+        f.make(1000, 1000).unwrap();
+
+        // 1% skimmed from the maker's mint_b
+        f.set_fee(100, false);
+        let tx = f.take(500).unwrap();
+
+        let taken = events::<crate::events::EscrowTaken>(&tx.logs);
+        assert_eq!(taken[0].paid, 500);
+        assert_eq!(taken[0].fee, 5);
+        assert!(!taken[0].fee_on_mint_a);
+
+        assert_eq!(token_amount(&f.program, &f.taker_ata_a), 500);
+        assert_eq!(token_amount(&f.program, &f.maker_ata_b), 495);
+        assert_eq!(token_amount(&f.program, &f.treasury_ata_b), 5);
+
+        // 1% skimmed from the taker's mint_a, but only if the taker accepts it
+        f.set_fee(100, true);
+        assert_error(
+            f.take_with(crate::instruction::Take {
+                amount: 500,
+                expected_deposit: 500,
+                expected_receive: 500,
+                max_fee_bps: 99,
+                payment_option: 0,
+                proof: vec![],
+            }),
+            ErrorCode::FeeAboveMax,
+        );
+        f.take(500).unwrap();
+
+        assert_eq!(token_amount(&f.program, &f.taker_ata_a), 995);
+        assert_eq!(token_amount(&f.program, &f.treasury_ata_a), 5);
+        assert_eq!(token_amount(&f.program, &f.maker_ata_b), 995);
+    }
+
+    #[test]
+    fn test_initialize_config() {
+        let (mut program, admin) = setup();
+        let program_data = deploy_upgradeable(&mut program, addr_to_pubkey(&admin.pubkey()));
+        let treasury = Pubkey::new_unique();
+
+        let impostor = Keypair::new();
+        program
+            .airdrop(&impostor.pubkey(), LAMPORTS_PER_SOL)
+            .unwrap();
+
+        let init_tx = |signer: &Keypair, blockhash| {
+            let init_ix = to_instruction(
+                crate::accounts::InitializeConfig {
+                    admin: addr_to_pubkey(&signer.pubkey()),
+                    config: config_pda().0,
+                    program: PROGRAM_ID,
+                    program_data,
+                    system_program: anchor_lang::system_program::ID,
+                }
+                .to_account_metas(None),
+                crate::instruction::InitializeConfig {
+                    treasury,
+                    fee_bps: 100,
+                    fee_on_mint_a: true,
+                }
+                .data(),
+            );
+            let message = Message::new(&[init_ix], Some(&signer.pubkey()));
+            Transaction::new(&[signer], message, blockhash)
+        };
+        // Only the upgrade authority can claim the admin role
+        assert_error(
+            program.send_transaction(init_tx(&impostor, program.latest_blockhash())),
+            ErrorCode::Unauthorized,
+        );
+
+        let tx = program
+            .send_transaction(init_tx(&admin, program.latest_blockhash()))
+            .unwrap();
+        let updated = events::<crate::events::ConfigUpdated>(&tx.logs);
+        assert_eq!(updated[0].fee_bps, 100);
+
+        let account = program
+            .get_account(&pubkey_to_addr(&config_pda().0))
+            .unwrap();
+        let config = crate::state::Config::try_deserialize(&mut account.data.as_ref()).unwrap();
+        assert_eq!(config.admin, addr_to_pubkey(&admin.pubkey()));
+        assert_eq!(config.treasury, treasury);
+        assert_eq!(config.fee_bps, 100);
+        assert!(config.fee_on_mint_a);
+
+        // The config can only be claimed once
+        program.expire_blockhash();
+        assert!(program
+            .send_transaction(init_tx(&admin, program.latest_blockhash()))
+            .is_err());
+    }
+
+    #[test]
+    fn test_pause() {
+        let mut f = Fixture::new();
+        f.make(10, 10).unwrap();
+
+        let maker = f.maker.insecure_clone();
+        let taker = f.taker.insecure_clone();
+
+        // Only the admin can pause
+        assert_error(
+            f.set_pause(&taker, crate::state::PAUSE_ALL),
+            ErrorCode::Unauthorized,
+        );
+
+        let tx = f.set_pause(&maker, crate::state::PAUSE_TAKE).unwrap();
+        let updated = events::<crate::events::ConfigUpdated>(&tx.logs);
+        assert_eq!(updated[0].paused, crate::state::PAUSE_TAKE);
+
+        assert_error(f.take(5), ErrorCode::Paused);
+
+        f.set_pause(&maker, 0).unwrap();
+        f.take(5).unwrap();
+        assert_eq!(token_amount(&f.program, &f.taker_ata_a), 5);
+
+        // Hand the switch to the taker; the old admin loses it
+        let transfer_ix = to_instruction(
+            crate::accounts::UpdateConfig {
+                admin: addr_to_pubkey(&maker.pubkey()),
+                config: config_pda().0,
+            }
+            .to_account_metas(None),
+            crate::instruction::TransferAdmin {
+                new_admin: addr_to_pubkey(&taker.pubkey()),
+            }
+            .data(),
+        );
+        f.send(transfer_ix, &maker).unwrap();
+        assert_eq!(f.config_data().admin, addr_to_pubkey(&taker.pubkey()));
+        assert_error(
+            f.set_pause(&maker, crate::state::PAUSE_ALL),
+            ErrorCode::Unauthorized,
+        );
+
+        f.set_pause(&taker, crate::state::PAUSE_ALL).unwrap();
+        assert_error(f.take(5), ErrorCode::Paused);
+
+        // Makers can still get their funds back while paused
+        let refund_ix = to_instruction(
+            crate::accounts::Refund {
+                maker: addr_to_pubkey(&maker.pubkey()),
+                rent_payer: addr_to_pubkey(&maker.pubkey()),
+                mint_a: addr_to_pubkey(&f.mint_a),
+                maker_ata_a: f.leg_a(addr_to_pubkey(&f.maker_ata_a)),
+                escrow: f.escrow,
+                vault: f.leg_a(f.vault),
+                token_program: f.token_program_a,
+                system_program: anchor_lang::system_program::ID,
+            }
+            .to_account_metas(None),
+            crate::instruction::Refund.data(),
+        );
+        f.send(refund_ix, &maker).unwrap();
+        assert!(f.program.get_account(&pubkey_to_addr(&f.escrow)).is_none());
+    }
+
+    #[test]
+    fn test_take_native_sol_deposit() {
+        let mut f = Fixture::with_native(true, false);
+        let deposit = LAMPORTS_PER_SOL;
+
+        f.make(deposit, 100).unwrap();
+
+        // No vault: the escrow holds the deposit itself
+        assert!(f.program.get_account(&pubkey_to_addr(&f.vault)).is_none());
+        let escrow_account = f.program.get_account(&pubkey_to_addr(&f.escrow)).unwrap();
+        assert_eq!(
+            escrow_account.lamports,
+            f.program
+                .minimum_balance_for_rent_exemption(escrow_account.data.len())
+                + deposit
+        );
+
+        let taker_before = f.program.get_balance(&f.taker.pubkey()).unwrap();
+        f.take(deposit / 4).unwrap();
+        let taker_after = f.program.get_balance(&f.taker.pubkey()).unwrap();
+
+        assert_eq!(taker_after, taker_before + deposit / 4 - 5000);
+        assert_eq!(token_amount(&f.program, &f.maker_ata_b), 25);
+
+        let maker_before = f.program.get_balance(&f.maker.pubkey()).unwrap();
+        f.take(deposit * 3 / 4).unwrap();
+        let maker_after = f.program.get_balance(&f.maker.pubkey()).unwrap();
+
+        // The maker gets the escrow's rent back
+        assert_eq!(
+            maker_after,
+            maker_before + escrow_account.lamports - deposit
+        );
+        assert_eq!(token_amount(&f.program, &f.maker_ata_b), 100);
+        assert!(f.program.get_account(&pubkey_to_addr(&f.escrow)).is_none());
+    }
+
+    #[test]
+    fn test_take_native_sol_payment() {
+        let mut f = Fixture::with_native(false, true);
+        let receive = LAMPORTS_PER_SOL;
+
+        f.make(10, receive).unwrap();
+
+        let escrow_rent = f.program.get_balance(&pubkey_to_addr(&f.escrow)).unwrap();
+        let vault_rent = f.program.get_balance(&pubkey_to_addr(&f.vault)).unwrap();
+        let maker_before = f.program.get_balance(&f.maker.pubkey()).unwrap();
+        let taker_before = f.program.get_balance(&f.taker.pubkey()).unwrap();
+
+        f.take(10).unwrap();
+
+        assert_eq!(token_amount(&f.program, &f.taker_ata_a), 10);
+        assert_eq!(
+            f.program.get_balance(&f.maker.pubkey()).unwrap(),
+            maker_before + receive + escrow_rent + vault_rent
+        );
+        assert_eq!(
+            f.program.get_balance(&f.taker.pubkey()).unwrap(),
+            taker_before - receive - 5000
+        );
+    }
+
+    #[test]
+    fn test_refund_native_sol() {
+        let mut f = Fixture::with_native(true, false);
+        let deposit = LAMPORTS_PER_SOL;
+
+        let maker_before = f.program.get_balance(&f.maker.pubkey()).unwrap();
+        f.make(deposit, 100).unwrap();
+
+        let maker = f.maker.insecure_clone();
+        let refund_ix = to_instruction(
+            crate::accounts::Refund {
+                maker: addr_to_pubkey(&maker.pubkey()),
+                rent_payer: addr_to_pubkey(&maker.pubkey()),
+                mint_a: addr_to_pubkey(&f.mint_a),
+                maker_ata_a: None,
+                escrow: f.escrow,
+                vault: None,
+                token_program: f.token_program_a,
+                system_program: anchor_lang::system_program::ID,
+            }
+            .to_account_metas(None),
+            crate::instruction::Refund.data(),
+        );
+        let tx = f.send(refund_ix, &maker).unwrap();
+
+        let refunded = events::<crate::events::EscrowRefunded>(&tx.logs);
+        assert_eq!(refunded[0].amount, deposit);
+
+        // Everything but the two transaction fees comes back
+        assert_eq!(
+            f.program.get_balance(&maker.pubkey()).unwrap(),
+            maker_before - 2 * 5000
+        );
+        assert!(f.program.get_account(&pubkey_to_addr(&f.escrow)).is_none());
+    }
+
+    fn basket_pda(maker: &Pubkey, seed: u64) -> Pubkey {
+        Pubkey::find_program_address(
+            &[b"basket", maker.as_ref(), &seed.to_le_bytes()],
+            &PROGRAM_ID,
+        )
+        .0
+    }
+
+    /// A basket offering 100 mint_a and 50 mint_c for 30 mint_b and 20 mint_d,
+    /// with the maker, taker and treasury holding ATAs for all four mints.
+    struct BasketFixture {
+        f: Fixture,
+        mint_c: Address,
+        mint_d: Address,
+        token_program_c: Pubkey,
+        maker_ata_c: Address,
+        maker_ata_d: Address,
+        taker_ata_c: Address,
+        taker_ata_d: Address,
+        treasury_ata_c: Address,
+        treasury_ata_d: Address,
+        basket: Pubkey,
+    }
+
+    // Returns `TransactionResult` like `Fixture`
+    #[allow(clippy::result_large_err)]
+    impl BasketFixture {
+        fn new() -> Self {
+            let mut f = Fixture::new();
+            let maker = f.maker.insecure_clone();
+
+            let mint_c = CreateMint::new(&mut f.program, &maker)
+                .decimals(6)
+                .authority(&maker.pubkey())
+                .send()
+                .unwrap();
+
+            Self::with_mint_c(f, mint_c, spl_token::ID)
+        }
+
+        /// Offers `mint_c` under `token_program_c` alongside the fixture's mint_a.
+        fn with_mint_c(mut f: Fixture, mint_c: Address, token_program_c: Pubkey) -> Self {
+            let maker = f.maker.insecure_clone();
+
+            let mint_d = CreateMint::new(&mut f.program, &maker)
+                .decimals(6)
+                .authority(&maker.pubkey())
+                .send()
+                .unwrap();
+
+            let taker = f.taker.pubkey();
+            let treasury = pubkey_to_addr(&f.treasury);
+            let mut ata = |mint: &Address, token_program: &Pubkey, owner: &Address, amount: u64| {
+                let token_program = pubkey_to_addr(token_program);
+                let ata = CreateAssociatedTokenAccount::new(&mut f.program, &maker, mint)
+                    .owner(owner)
+                    .token_program_id(&token_program)
+                    .send()
+                    .unwrap();
+                if amount > 0 {
+                    MintTo::new(&mut f.program, &maker, mint, &ata, amount)
+                        .token_program_id(&token_program)
+                        .send()
+                        .unwrap();
+                }
+                ata
+            };
+
+            let maker_ata_c = ata(&mint_c, &token_program_c, &maker.pubkey(), 1000);
+            let maker_ata_d = ata(&mint_d, &spl_token::ID, &maker.pubkey(), 0);
+            let taker_ata_c = ata(&mint_c, &token_program_c, &taker, 0);
+            let taker_ata_d = ata(&mint_d, &spl_token::ID, &taker, 1000);
+            let treasury_ata_c = ata(&mint_c, &token_program_c, &treasury, 0);
+            let treasury_ata_d = ata(&mint_d, &spl_token::ID, &treasury, 0);
+
+            let basket = basket_pda(&addr_to_pubkey(&maker.pubkey()), f.seed);
+
+            Self {
+                f,
+                mint_c,
+                mint_d,
+                token_program_c,
+                maker_ata_c,
+                maker_ata_d,
+                taker_ata_c,
+                taker_ata_d,
+                treasury_ata_c,
+                treasury_ata_d,
+                basket,
+            }
+        }
+
+        fn token_program(&self, mint: &Address) -> Pubkey {
+            if *mint == self.mint_c {
+                self.token_program_c
+            } else {
+                spl_token::ID
+            }
+        }
+
+        fn vault(&self, mint: &Address) -> Pubkey {
+            associated_token::get_associated_token_address_with_program_id(
+                &self.basket,
+                &addr_to_pubkey(mint),
+                &self.token_program(mint),
+            )
+        }
+
+        /// `[mint, token_program, ..accounts]` for one leg. Token-2022 mints are
+        /// passed writable so that transfer fees withheld in a vault can be harvested.
+        fn group(
+            &self,
+            mint: &Address,
+            accounts: &[Pubkey],
+        ) -> Vec<anchor_lang::prelude::AccountMeta> {
+            let token_program = self.token_program(mint);
+            let mut metas = vec![
+                anchor_lang::prelude::AccountMeta {
+                    pubkey: addr_to_pubkey(mint),
+                    is_signer: false,
+                    is_writable: token_program == token_2022::ID,
+                },
+                anchor_lang::prelude::AccountMeta::new_readonly(token_program, false),
+            ];
+            metas.extend(
+                accounts
+                    .iter()
+                    .map(|account| anchor_lang::prelude::AccountMeta::new(*account, false)),
+            );
+            metas
+        }
+
+        fn make(&mut self) -> TransactionResult {
+            self.make_with(None, None)
+        }
+
+        fn make_with(
+            &mut self,
+            not_before: Option<i64>,
+            not_after: Option<i64>,
+        ) -> TransactionResult {
+            let f = &self.f;
+            let mut accounts = crate::accounts::MakeBasket {
+                maker: addr_to_pubkey(&f.maker.pubkey()),
+                basket: self.basket,
+                config: config_pda().0,
+                associated_token_program: associated_token::spl_associated_token_account::ID,
+                system_program: anchor_lang::system_program::ID,
+            }
+            .to_account_metas(None);
+            accounts.extend(self.group(
+                &f.mint_a,
+                &[addr_to_pubkey(&f.maker_ata_a), self.vault(&f.mint_a)],
+            ));
+            accounts.extend(self.group(
+                &self.mint_c,
+                &[addr_to_pubkey(&self.maker_ata_c), self.vault(&self.mint_c)],
+            ));
+            accounts.extend(self.group(&f.mint_b, &[]));
+            accounts.extend(self.group(&self.mint_d, &[]));
+
+            let ix = to_instruction(
+                accounts,
+                crate::instruction::MakeBasket {
+                    seed: f.seed,
+                    offered: vec![
+                        crate::state::BasketLeg {
+                            mint: addr_to_pubkey(&f.mint_a),
+                            amount: 100,
+                        },
+                        crate::state::BasketLeg {
+                            mint: addr_to_pubkey(&self.mint_c),
+                            amount: 50,
+                        },
+                    ],
+                    requested: vec![
+                        crate::state::BasketLeg {
+                            mint: addr_to_pubkey(&f.mint_b),
+                            amount: 30,
+                        },
+                        crate::state::BasketLeg {
+                            mint: addr_to_pubkey(&self.mint_d),
+                            amount: 20,
+                        },
+                    ],
+                    not_before,
+                    not_after,
+                }
+                .data(),
+            );
+            let maker = self.f.maker.insecure_clone();
+            self.f.send(ix, &maker)
+        }
+
+        fn take(&mut self, max_fee_bps: u16) -> TransactionResult {
+            let ix = self.take_ix(max_fee_bps);
+            let taker = self.f.taker.insecure_clone();
+            self.f.send(ix, &taker)
+        }
+
+        fn take_ix(&self, max_fee_bps: u16) -> Instruction {
+            let f = &self.f;
+            let mut accounts = crate::accounts::TakeBasket {
+                taker: addr_to_pubkey(&f.taker.pubkey()),
+                maker: addr_to_pubkey(&f.maker.pubkey()),
+                basket: self.basket,
+                config: config_pda().0,
+            }
+            .to_account_metas(None);
+            // Requested legs first, then offered legs
+            accounts.extend(self.group(
+                &f.mint_b,
+                &[
+                    addr_to_pubkey(&f.taker_ata_b),
+                    addr_to_pubkey(&f.maker_ata_b),
+                    addr_to_pubkey(&f.treasury_ata_b),
+                ],
+            ));
+            accounts.extend(self.group(
+                &self.mint_d,
+                &[
+                    addr_to_pubkey(&self.taker_ata_d),
+                    addr_to_pubkey(&self.maker_ata_d),
+                    addr_to_pubkey(&self.treasury_ata_d),
+                ],
+            ));
+            accounts.extend(self.group(
+                &f.mint_a,
+                &[
+                    self.vault(&f.mint_a),
+                    addr_to_pubkey(&f.taker_ata_a),
+                    addr_to_pubkey(&f.maker_ata_a),
+                    addr_to_pubkey(&f.treasury_ata_a),
+                ],
+            ));
+            accounts.extend(self.group(
+                &self.mint_c,
+                &[
+                    self.vault(&self.mint_c),
+                    addr_to_pubkey(&self.taker_ata_c),
+                    addr_to_pubkey(&self.maker_ata_c),
+                    addr_to_pubkey(&self.treasury_ata_c),
+                ],
+            ));
+
+            to_instruction(
+                accounts,
+                crate::instruction::TakeBasket { max_fee_bps }.data(),
+            )
+        }
+    }
+
+    #[test]
+    fn test_basket_take() {
+        let mut b = BasketFixture::new();
+        let tx = b.make().unwrap();
+
+        let made = events::<crate::events::BasketMade>(&tx.logs);
+        assert_eq!(made[0].offered.len(), 2);
+        assert_eq!(made[0].requested.len(), 2);
+        assert_eq!(
+            token_amount(&b.f.program, &pubkey_to_addr(&b.vault(&b.f.mint_a))),
+            100
+        );
+        assert_eq!(
+            token_amount(&b.f.program, &pubkey_to_addr(&b.vault(&b.mint_c))),
+            50
+        );
+
+        // 10% skimmed from each requested leg, but only if the taker accepts it
+        b.f.set_fee(1000, false);
+        assert_error(b.take(999), ErrorCode::FeeAboveMax);
+        let tx = b.take(1000).unwrap();
+
+        let taken = events::<crate::events::BasketTaken>(&tx.logs);
+        assert_eq!(taken[0].fee_bps, 1000);
+        assert!(!taken[0].fee_on_offered);
+        assert_eq!(token_amount(&b.f.program, &b.f.taker_ata_a), 100);
+        assert_eq!(token_amount(&b.f.program, &b.taker_ata_c), 50);
+        assert_eq!(token_amount(&b.f.program, &b.f.maker_ata_b), 27);
+        assert_eq!(token_amount(&b.f.program, &b.maker_ata_d), 18);
+        assert_eq!(token_amount(&b.f.program, &b.f.treasury_ata_b), 3);
+        assert_eq!(token_amount(&b.f.program, &b.treasury_ata_d), 2);
+
+        assert!(b
+            .f
+            .program
+            .get_account(&pubkey_to_addr(&b.vault(&b.f.mint_a)))
+            .is_none());
+        assert!(b
+            .f
+            .program
+            .get_account(&pubkey_to_addr(&b.basket))
+            .is_none());
+    }
+
+    #[test]
+    fn test_basket_take_fee_on_offered() {
+        let mut b = BasketFixture::new();
+        b.make().unwrap();
+
+        b.f.set_fee(1000, true);
+        b.take(1000).unwrap();
+
+        assert_eq!(token_amount(&b.f.program, &b.f.taker_ata_a), 90);
+        assert_eq!(token_amount(&b.f.program, &b.taker_ata_c), 45);
+        assert_eq!(token_amount(&b.f.program, &b.f.treasury_ata_a), 10);
+        assert_eq!(token_amount(&b.f.program, &b.treasury_ata_c), 5);
+        assert_eq!(token_amount(&b.f.program, &b.f.maker_ata_b), 30);
+        assert_eq!(token_amount(&b.f.program, &b.maker_ata_d), 20);
+    }
+
+    #[test]
+    fn test_basket_take_window() {
+        let mut b = BasketFixture::new();
+        let now = b.f.program.get_sysvar::<Clock>().unix_timestamp;
+
+        assert_error(
+            b.make_with(Some(now + 100), Some(now)),
+            ErrorCode::InvalidTakeWindow,
+        );
+        let tx = b.make_with(Some(now + 100), Some(now + 200)).unwrap();
+        let made = events::<crate::events::BasketMade>(&tx.logs);
+        assert_eq!(made[0].not_after, Some(now + 200));
+
+        assert_error(b.take(0), ErrorCode::TooEarlyToTake);
+
+        b.f.warp_to(now + 201);
+        assert_error(b.take(0), ErrorCode::EscrowExpired);
+
+        b.f.warp_to(now + 150);
+        b.take(0).unwrap();
+        assert_eq!(token_amount(&b.f.program, &b.f.taker_ata_a), 100);
+    }
+
+    #[test]
+    fn test_make_basket_precreated_vault() {
+        // An empty vault created ahead of time does not block the basket
+        let mut b = BasketFixture::new();
+        let maker = b.f.maker.insecure_clone();
+        CreateAssociatedTokenAccount::new(&mut b.f.program, &maker, &b.mint_c)
+            .owner(&pubkey_to_addr(&b.basket))
+            .send()
+            .unwrap();
+        b.make().unwrap();
+        assert_eq!(
+            token_amount(&b.f.program, &pubkey_to_addr(&b.vault(&b.mint_c))),
+            50
+        );
+
+        // One that already holds tokens does
+        let mut b = BasketFixture::new();
+        let maker = b.f.maker.insecure_clone();
+        let vault = CreateAssociatedTokenAccount::new(&mut b.f.program, &maker, &b.mint_c)
+            .owner(&pubkey_to_addr(&b.basket))
+            .send()
+            .unwrap();
+        MintTo::new(&mut b.f.program, &maker, &b.mint_c, &vault, 1)
+            .send()
+            .unwrap();
+        assert_error(b.make(), ErrorCode::VaultNotEmpty);
+    }
+
+    #[test]
+    fn test_basket_refund() {
+        let mut b = BasketFixture::new();
+        b.make().unwrap();
+
+        let f = &b.f;
+        let mut accounts = crate::accounts::RefundBasket {
+            maker: addr_to_pubkey(&f.maker.pubkey()),
+            basket: b.basket,
+        }
+        .to_account_metas(None);
+        accounts.extend(b.group(
+            &f.mint_a,
+            &[b.vault(&f.mint_a), addr_to_pubkey(&f.maker_ata_a)],
+        ));
+
+        // Every vault has to be emptied
+        let ix = to_instruction(accounts.clone(), crate::instruction::RefundBasket.data());
+        let maker = b.f.maker.insecure_clone();
+        assert_error(b.f.send(ix, &maker), ErrorCode::InvalidBasketAccounts);
+
+        accounts.extend(b.group(
+            &b.mint_c,
+            &[b.vault(&b.mint_c), addr_to_pubkey(&b.maker_ata_c)],
+        ));
+        let ix = to_instruction(accounts, crate::instruction::RefundBasket.data());
+        let tx = b.f.send(ix, &maker).unwrap();
+
+        let refunded = events::<crate::events::BasketRefunded>(&tx.logs);
+        assert_eq!(refunded[0].offered[1].amount, 50);
+        assert_eq!(token_amount(&b.f.program, &b.f.maker_ata_a), 1000000000);
+        assert_eq!(token_amount(&b.f.program, &b.maker_ata_c), 1000);
+        assert!(b
+            .f
+            .program
+            .get_account(&pubkey_to_addr(&b.basket))
+            .is_none());
+    }
+
+    /// A basket offering a Token-2022 mint with a 1% transfer fee alongside mint_a.
+    fn transfer_fee_basket() -> BasketFixture {
+        let mut f = Fixture::new();
+        let maker = f.maker.insecure_clone();
+        let mint_c = create_transfer_fee_mint(&mut f.program, &maker, 100);
+
+        BasketFixture::with_mint_c(f, mint_c, token_2022::ID)
+    }
+
+    /// Marks `account` readonly wherever `ix` passes it.
+    fn set_readonly(ix: &mut Instruction, account: &Address) {
+        for meta in ix
+            .accounts
+            .iter_mut()
+            .filter(|meta| meta.pubkey == *account)
+        {
+            meta.is_writable = false;
+        }
+    }
+
+    #[test]
+    fn test_basket_take_transfer_fee() {
+        let mut b = transfer_fee_basket();
+        let tx = b.make().unwrap();
+
+        // 1 of the 50 mint_c is withheld on the way into the vault
+        let made = events::<crate::events::BasketMade>(&tx.logs);
+        assert_eq!(made[0].offered[1].amount, 49);
+
+        // The withheld fee is harvested to the mint before the vault closes
+        let mut ix = b.take_ix(0);
+        set_readonly(&mut ix, &b.mint_c);
+        let taker = b.f.taker.insecure_clone();
+        assert_error(
+            b.f.send(ix, &taker),
+            anchor_lang::error::ErrorCode::AccountNotMutable,
+        );
+
+        b.take(0).unwrap();
+        assert_eq!(token_amount(&b.f.program, &b.taker_ata_c), 48);
+        assert_eq!(token_amount(&b.f.program, &b.f.taker_ata_a), 100);
+        assert!(b
+            .f
+            .program
+            .get_account(&pubkey_to_addr(&b.vault(&b.mint_c)))
+            .is_none());
+    }
+
+    #[test]
+    fn test_basket_refund_transfer_fee() {
+        let mut b = transfer_fee_basket();
+        b.make().unwrap();
+
+        let f = &b.f;
+        let mut accounts = crate::accounts::RefundBasket {
+            maker: addr_to_pubkey(&f.maker.pubkey()),
+            basket: b.basket,
+        }
+        .to_account_metas(None);
+        accounts.extend(b.group(
+            &f.mint_a,
+            &[b.vault(&f.mint_a), addr_to_pubkey(&f.maker_ata_a)],
+        ));
+        accounts.extend(b.group(
+            &b.mint_c,
+            &[b.vault(&b.mint_c), addr_to_pubkey(&b.maker_ata_c)],
+        ));
+        let ix = to_instruction(accounts, crate::instruction::RefundBasket.data());
+        let maker = b.f.maker.insecure_clone();
+
+        let mut readonly_ix = ix.clone();
+        set_readonly(&mut readonly_ix, &b.mint_c);
+        assert_error(
+            b.f.send(readonly_ix, &maker),
+            anchor_lang::error::ErrorCode::AccountNotMutable,
+        );
+
+        let tx = b.f.send(ix, &maker).unwrap();
+
+        // Another 1 is withheld on the way back
+        let refunded = events::<crate::events::BasketRefunded>(&tx.logs);
+        assert_eq!(refunded[0].offered[1].amount, 49);
+        assert_eq!(token_amount(&b.f.program, &b.maker_ata_c), 998);
+        assert!(b
+            .f
+            .program
+            .get_account(&pubkey_to_addr(&b.basket))
+            .is_none());
+    }
+
+    #[test]
+    fn test_take_payment_option() {
+        let mut f = Fixture::new();
+        let maker = f.maker.insecure_clone();
+        let taker = f.taker.insecure_clone();
+
+        // A second stablecoin the maker also accepts
+        let mint_c = CreateMint::new(&mut f.program, &maker)
+            .decimals(6)
+            .authority(&maker.pubkey())
+            .send()
+            .unwrap();
+        let maker_ata_c = CreateAssociatedTokenAccount::new(&mut f.program, &maker, &mint_c)
+            .owner(&maker.pubkey())
+            .send()
+            .unwrap();
+        let taker_ata_c = CreateAssociatedTokenAccount::new(&mut f.program, &maker, &mint_c)
+            .owner(&taker.pubkey())
+            .send()
+            .unwrap();
+        MintTo::new(&mut f.program, &maker, &mint_c, &taker_ata_c, 1000)
+            .send()
+            .unwrap();
+
+        f.make(10, 20).unwrap();
+
+        let set_ix = to_instruction(
+            crate::accounts::SetPaymentOption {
+                maker: addr_to_pubkey(&maker.pubkey()),
+                mint: addr_to_pubkey(&mint_c),
+                escrow: f.escrow,
+                config: config_pda().0,
+            }
+            .to_account_metas(None),
+            crate::instruction::SetPaymentOption { receive: 40 }.data(),
+        );
+        f.send(set_ix, &maker).unwrap();
+        assert_eq!(f.escrow_data().payment_options[0].receive, 40);
+
+        let option_accounts = crate::accounts::Take {
+            mint_b: addr_to_pubkey(&mint_c),
+            taker_ata_b: Some(addr_to_pubkey(&taker_ata_c)),
+            maker_ata_b: Some(addr_to_pubkey(&maker_ata_c)),
+            treasury_ata: None,
+            ..f.take_accounts()
+        };
+        let take_ix = |payment_option: u8, expected_receive: u64| {
+            to_instruction(
+                option_accounts.to_account_metas(None),
+                crate::instruction::Take {
+                    amount: 5,
+                    expected_deposit: 10,
+                    expected_receive,
+                    max_fee_bps: 0,
+                    payment_option,
+                    proof: vec![],
+                }
+                .data(),
+            )
+        };
+
+        // Paying in mint_c under mint_b's option is rejected
+        assert_error(
+            f.send(take_ix(0, 20), &taker),
+            ErrorCode::ConstraintTokenMint,
+        );
+
+        f.send(take_ix(1, 40), &taker).unwrap();
+        assert_eq!(token_amount(&f.program, &maker_ata_c), 20);
+        assert_eq!(token_amount(&f.program, &f.taker_ata_a), 5);
+
+        // Every option is scaled down by the fill
+        let escrow_data = f.escrow_data();
+        assert_eq!(escrow_data.remaining, 5);
+        assert_eq!(escrow_data.receive, 10);
+        assert_eq!(escrow_data.payment_options[0].receive, 20);
+
+        f.take(5).unwrap();
+        assert_eq!(token_amount(&f.program, &f.maker_ata_b), 10);
+        assert_eq!(token_amount(&f.program, &f.taker_ata_a), 10);
+    }
+
+    #[test]
+    fn test_take_oracle_priced() {
+        let mut f = Fixture::new();
+        let now = f.program.get_sysvar::<Clock>().unix_timestamp;
+        let feed = Pubkey::new_unique();
+
+        // 1 mint_a = 2.50 mint_b, both with 6 decimals
+        set_price(&mut f.program, feed, [7; 32], 250_000_000, 100_000, -8, now);
+
+        f.make_with(crate::state::MakeTerms {
+            pricing: crate::state::Pricing::Oracle(crate::state::OraclePricing {
+                feed,
+                feed_id: [7; 32],
+                spread_bps: 100,
+                max_age: 60,
+                max_confidence_bps: 50,
+            }),
+            ..f.make_args(1000, 0)
+        })
+        .unwrap();
+
+        let accounts = crate::accounts::Take {
+            price_feed: Some(feed),
+            ..f.take_accounts()
+        };
+        let take_ix = |amount: u64, expected_receive: u64| {
+            to_instruction(
+                accounts.to_account_metas(None),
+                crate::instruction::Take {
+                    amount,
+                    expected_deposit: 1000,
+                    expected_receive,
+                    max_fee_bps: 0,
+                    payment_option: 0,
+                    proof: vec![],
+                }
+                .data(),
+            )
+        };
+        let taker = f.taker.insecure_clone();
+
+        // All 1000 currently cost 1000 * 2.5 * 1.01 = 2525
+        assert_error(f.send(take_ix(400, 2524), &taker), ErrorCode::TermsMismatch);
+
+        f.warp_to(now + 61);
+        assert_error(f.send(take_ix(400, 2525), &taker), ErrorCode::StalePrice);
+
+        set_price(
+            &mut f.program,
+            feed,
+            [7; 32],
+            250_000_000,
+            2_000_000,
+            -8,
+            now + 61,
+        );
+        assert_error(
+            f.send(take_ix(400, 2525), &taker),
+            ErrorCode::PriceConfidenceTooWide,
+        );
+
+        // A fresh price, but for some other feed
+        set_price(
+            &mut f.program,
+            feed,
+            [8; 32],
+            250_000_000,
+            100_000,
+            -8,
+            now + 61,
+        );
+        assert_error(
+            f.send(take_ix(400, 2525), &taker),
+            ErrorCode::InvalidPriceFeed,
+        );
+
+        set_price(
+            &mut f.program,
+            feed,
+            [7; 32],
+            250_000_000,
+            100_000,
+            -8,
+            now + 61,
+        );
+        f.send(take_ix(400, 2525), &taker).unwrap();
+
+        assert_eq!(token_amount(&f.program, &f.taker_ata_a), 400);
+        assert_eq!(token_amount(&f.program, &f.maker_ata_b), 1010);
+    }
+
+    #[test]
+    fn test_take_dutch_auction() {
+        let mut f = Fixture::new();
+        let now = f.program.get_sysvar::<Clock>().unix_timestamp;
+        let dutch = crate::state::DutchPricing {
+            start_receive: 2000,
+            end_receive: 1000,
+            start_ts: now + 100,
+            end_ts: now + 1100,
+        };
+
+        assert_error(
+            f.make_with(crate::state::MakeTerms {
+                pricing: crate::state::Pricing::Dutch(crate::state::DutchPricing {
+                    end_receive: 3000,
+                    ..dutch
+                }),
+                ..f.make_args(1000, 0)
+            }),
+            ErrorCode::InvalidPricing,
+        );
+
+        let tx = f
+            .make_with(crate::state::MakeTerms {
+                pricing: crate::state::Pricing::Dutch(dutch),
+                ..f.make_args(1000, 0)
+            })
+            .unwrap();
+        let made = events::<crate::events::EscrowMade>(&tx.logs);
+        assert_eq!(made[0].pricing, crate::state::Pricing::Dutch(dutch));
+
+        // The auction sets the price, so it cannot be amended
+        let amend_ix = to_instruction(
+            crate::accounts::Amend {
+                maker: addr_to_pubkey(&f.maker.pubkey()),
+                escrow: f.escrow,
+                config: config_pda().0,
+            }
+            .to_account_metas(None),
+            crate::instruction::Amend {
+                receive: 1,
+                not_before: None,
+                not_after: None,
+            }
+            .data(),
+        );
+        let maker = f.maker.insecure_clone();
+        assert_error(f.send(amend_ix, &maker), ErrorCode::InvalidPricing);
+
+        // Halfway through the auction all 1000 cost 1500
+        f.warp_to(now + 600);
+        assert_error(
+            f.take_with(crate::instruction::Take {
+                amount: 400,
+                expected_deposit: 1000,
+                expected_receive: 1499,
+                max_fee_bps: 0,
+                payment_option: 0,
+                proof: vec![],
+            }),
+            ErrorCode::TermsMismatch,
+        );
+        f.take_with(crate::instruction::Take {
+            amount: 400,
+            expected_deposit: 1000,
+            expected_receive: 1500,
+            max_fee_bps: 0,
+            payment_option: 0,
+            proof: vec![],
+        })
+        .unwrap();
+        assert_eq!(token_amount(&f.program, &f.maker_ata_b), 600);
+
+        // Past the end the price rests at the floor
+        f.warp_to(now + 5000);
+        f.take_with(crate::instruction::Take {
+            amount: 600,
+            expected_deposit: 600,
+            expected_receive: 600,
+            max_fee_bps: 0,
+            payment_option: 0,
+            proof: vec![],
+        })
+        .unwrap();
+
+        assert_eq!(token_amount(&f.program, &f.taker_ata_a), 1000);
+        assert_eq!(token_amount(&f.program, &f.maker_ata_b), 1200);
+    }
+
+    #[test]
+    fn test_take_creates_missing_atas() {
+        let mut f = Fixture::new();
+        f.make(10, 20).unwrap();
+
+        // A first-time taker without a mint_a account, and a maker who holds no mint_b
+        for account in [f.taker_ata_a, f.maker_ata_b] {
+            f.program.set_account(account, Account::default()).unwrap();
+        }
+        let taker_lamports = f.program.get_balance(&f.taker.pubkey()).unwrap();
+
+        f.take(4).unwrap();
+
+        assert_eq!(token_amount(&f.program, &f.taker_ata_a), 4);
+        assert_eq!(token_amount(&f.program, &f.maker_ata_b), 8);
+        // The taker funded both accounts
+        let rent = f
+            .program
+            .minimum_balance_for_rent_exemption(spl_token::state::Account::LEN);
+        assert!(f.program.get_balance(&f.taker.pubkey()).unwrap() <= taker_lamports - 2 * rent);
+
+        // Later fills reuse the accounts
+        f.take(6).unwrap();
+        assert_eq!(token_amount(&f.program, &f.taker_ata_a), 10);
+        assert_eq!(token_amount(&f.program, &f.maker_ata_b), 20);
+    }
+
+    #[test]
+    fn test_make_with_precreated_vault() {
+        let mut f = Fixture::new();
+        let griefer = Keypair::new();
+        f.program
+            .airdrop(&griefer.pubkey(), LAMPORTS_PER_SOL)
+            .unwrap();
+
+        // Anyone can create the escrow's ATA ahead of the maker
+        let vault = CreateAssociatedTokenAccount::new(&mut f.program, &griefer, &f.mint_a)
+            .owner(&pubkey_to_addr(&f.escrow))
+            .send()
+            .unwrap();
+        assert_eq!(vault, pubkey_to_addr(&f.vault));
+
+        f.make(10, 20).unwrap();
+        assert_eq!(token_amount(&f.program, &vault), 10);
+        assert_eq!(f.escrow_data().deposit, 10);
+
+        // ...but one already holding tokens would inflate the deposit
+        let mut f = Fixture::new();
+        let vault = CreateAssociatedTokenAccount::new(&mut f.program, &griefer, &f.mint_a)
+            .owner(&pubkey_to_addr(&f.escrow))
+            .send()
+            .unwrap();
+        let maker = f.maker.insecure_clone();
+        MintTo::new(&mut f.program, &maker, &f.mint_a, &vault, 1)
+            .send()
+            .unwrap();
+        assert_error(f.make(10, 20), ErrorCode::VaultNotEmpty);
+    }
+
+    #[test]
+    fn test_make_rejects_misowned_vault() {
+        let mut f = Fixture::new();
+        let griefer = Keypair::new();
+
+        let mut data = vec![0; spl_token::state::Account::LEN];
+        spl_token::state::Account {
+            mint: addr_to_pubkey(&f.mint_a),
+            owner: addr_to_pubkey(&griefer.pubkey()),
+            state: spl_token::state::AccountState::Initialized,
+            ..Default::default()
+        }
+        .pack_into_slice(&mut data);
+        f.program
+            .set_account(
+                pubkey_to_addr(&f.vault),
+                Account {
+                    lamports: f.program.minimum_balance_for_rent_exemption(data.len()),
+                    data,
+                    owner: pubkey_to_addr(&spl_token::ID),
+                    executable: false,
+                    rent_epoch: 0,
+                },
+            )
+            .unwrap();
+
+        // Anchor's own ConstraintTokenOwner, raised before the escrow is touched
+        assert_error(f.make(10, 20), ErrorCode::ConstraintTokenOwner);
+    }
+
+    #[test]
+    fn test_make_sponsored_rent() {
+        let mut f = Fixture::new();
+        let maker = f.maker.insecure_clone();
+        let sponsor = Keypair::new();
+        f.program
+            .airdrop(&sponsor.pubkey(), LAMPORTS_PER_SOL)
+            .unwrap();
+        let sponsor_key = addr_to_pubkey(&sponsor.pubkey());
+
+        let sponsor_before = f.program.get_balance(&sponsor.pubkey()).unwrap();
+        let maker_before = f.program.get_balance(&maker.pubkey()).unwrap();
+
+        // The sponsor pays the transaction fee and all rent
+        let make_ix = to_instruction(
+            crate::accounts::Make {
+                payer: sponsor_key,
+                ..f.make_accounts()
+            }
+            .to_account_metas(None),
+            crate::instruction::Make {
+                seed: f.seed,
+                terms: f.make_args(10, 20),
+            }
+            .data(),
+        );
+        let message = Message::new(&[make_ix], Some(&sponsor.pubkey()));
+        let transaction =
+            Transaction::new(&[&sponsor, &maker], message, f.program.latest_blockhash());
+        let tx = f.program.send_transaction(transaction).unwrap();
+        f.program.expire_blockhash();
+
+        let made = events::<crate::events::EscrowMade>(&tx.logs);
+        assert_eq!(made[0].rent_payer, sponsor_key);
+        assert_eq!(f.escrow_data().rent_payer, sponsor_key);
+        assert_eq!(
+            f.program.get_balance(&maker.pubkey()).unwrap(),
+            maker_before
+        );
+
+        // Rent can only go back to the recorded payer
+        let to_maker = crate::accounts::Take {
+            rent_payer: addr_to_pubkey(&maker.pubkey()),
+            ..f.take_accounts()
+        }
+        .to_account_metas(None);
+        let to_sponsor = crate::accounts::Take {
+            rent_payer: sponsor_key,
+            ..f.take_accounts()
+        }
+        .to_account_metas(None);
+        let take_data = crate::instruction::Take {
+            amount: 4,
+            expected_deposit: 10,
+            expected_receive: 20,
+            max_fee_bps: 0,
+            payment_option: 0,
+            proof: vec![],
+        }
+        .data();
+        let taker = f.taker.insecure_clone();
+        assert_error(
+            f.send(to_instruction(to_maker, take_data.clone()), &taker),
+            anchor_lang::error::ErrorCode::ConstraintAddress,
+        );
+        f.send(to_instruction(to_sponsor, take_data), &taker)
+            .unwrap();
+
+        let refund_ix = to_instruction(
+            crate::accounts::Refund {
+                maker: addr_to_pubkey(&maker.pubkey()),
+                rent_payer: sponsor_key,
+                mint_a: addr_to_pubkey(&f.mint_a),
+                maker_ata_a: Some(addr_to_pubkey(&f.maker_ata_a)),
+                escrow: f.escrow,
+                vault: Some(f.vault),
+                token_program: f.token_program_a,
+                system_program: anchor_lang::system_program::ID,
+            }
+            .to_account_metas(None),
+            crate::instruction::Refund.data(),
+        );
+        f.send(refund_ix, &maker).unwrap();
+
+        // The sponsor is out only the make fee, the maker only the refund fee
+        assert_eq!(
+            f.program.get_balance(&sponsor.pubkey()).unwrap(),
+            sponsor_before - 2 * 5000
+        );
+        assert_eq!(
+            f.program.get_balance(&maker.pubkey()).unwrap(),
+            maker_before - 5000
+        );
+    }
+
+    #[test]
+    fn test_take_merkle_allowlist() {
+        let mut f = Fixture::new();
+        let taker = addr_to_pubkey(&f.taker.pubkey());
+
+        let mut takers: Vec<Pubkey> = (0..6).map(|_| Pubkey::new_unique()).collect();
+        takers.insert(3, taker);
+        let tree = crate::merkle::MerkleTree::new(&takers);
+
+        f.make_with(crate::state::MakeTerms {
+            taker_root: Some(tree.root()),
+            ..f.make_args(10, 20)
+        })
+        .unwrap();
+
+        let take = |proof: Vec<[u8; 32]>| crate::instruction::Take {
+            amount: 5,
+            expected_deposit: 10,
+            expected_receive: 20,
+            max_fee_bps: 0,
+            payment_option: 0,
+            proof,
+        };
+
+        assert_error(f.take_with(take(vec![])), ErrorCode::TakerNotAllowed);
+        assert_error(f.take_with(take(tree.proof(2))), ErrorCode::TakerNotAllowed);
+
+        let tx = f.take_with(take(tree.proof(3))).unwrap();
+        assert_eq!(events::<crate::events::EscrowTaken>(&tx.logs).len(), 1);
+        assert_eq!(token_amount(&f.program, &f.taker_ata_a), 5);
+
+        // The last leaf of an odd-sized layer is carried up without a sibling
+        for (index, key) in takers.iter().enumerate() {
+            assert!(crate::merkle::verify(&tree.root(), key, &tree.proof(index)));
+        }
+    }
 }
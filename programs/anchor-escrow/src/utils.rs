@@ -0,0 +1,360 @@
+use anchor_lang::prelude::*;
+use anchor_spl::{
+    token::{self, spl_token::native_mint},
+    token_2022::{
+        self,
+        spl_token_2022::{
+            extension::{
+                mint_close_authority::MintCloseAuthority, non_transferable::NonTransferable,
+                permanent_delegate::PermanentDelegate, transfer_fee::TransferFeeConfig,
+                transfer_hook::TransferHook, BaseStateWithExtensions, StateWithExtensions,
+            },
+            onchain::invoke_transfer_checked,
+            state::{Account as TokenAccountState, Mint as MintState},
+        },
+    },
+    token_2022_extensions::transfer_fee::{
+        harvest_withheld_tokens_to_mint, HarvestWithheldTokensToMint,
+    },
+    token_interface::{
+        close_account, transfer_checked, CloseAccount, Mint, TokenAccount, TransferChecked,
+    },
+};
+
+use crate::error::ErrorCode;
+use crate::state::{
+    BasketLeg, Config, Escrow, MINT_RISK_CLOSE_AUTHORITY, MINT_RISK_FREEZE_AUTHORITY,
+    MINT_RISK_PERMANENT_DELEGATE, MINT_RISK_TRANSFER_HOOK,
+};
+
+/// Whether `mint` is wrapped SOL. Escrow legs in this mint settle in native
+/// lamports, with the escrow PDA itself holding a native deposit.
+pub fn is_native(mint: &Pubkey) -> bool {
+    *mint == native_mint::ID
+}
+
+/// Unwraps a token account that may only be omitted on a native SOL leg.
+pub fn required<T>(account: Option<T>) -> Result<T> {
+    account.ok_or_else(|| error!(ErrorCode::TokenAccountRequired))
+}
+
+/// Lamports held by a program-owned account beyond its rent-exempt minimum.
+pub fn lamports_above_rent(account: &AccountInfo) -> Result<u64> {
+    let rent = Rent::get()?.minimum_balance(account.data_len());
+    Ok(account.lamports().saturating_sub(rent))
+}
+
+/// Returns how much has to be sent so that `net` arrives after the mint's
+/// Token-2022 transfer fee for the current epoch, or `net` if the mint has no fee.
+pub fn gross_transfer_amount(mint: &InterfaceAccount<Mint>, net: u64) -> Result<u64> {
+    let mint_info = mint.to_account_info();
+    let mint_data = mint_info.try_borrow_data()?;
+    let mint_state = StateWithExtensions::<MintState>::unpack(&mint_data)?;
+
+    let Ok(fee_config) = mint_state.get_extension::<TransferFeeConfig>() else {
+        return Ok(net);
+    };
+
+    let epoch = Clock::get()?.epoch;
+    let gross = fee_config
+        .get_epoch_fee(epoch)
+        .calculate_pre_fee_amount(net)
+        .ok_or(ErrorCode::MathOverflow)?;
+
+    Ok(gross)
+}
+
+/// Moves Token-2022 transfer fees withheld in `account` to the mint, since a
+/// token account holding withheld fees cannot be closed. The mint must be writable
+/// if it has a transfer fee.
+pub fn harvest_withheld_fees<'info>(
+    token_program: AccountInfo<'info>,
+    mint: AccountInfo<'info>,
+    account: AccountInfo<'info>,
+) -> Result<()> {
+    let has_transfer_fee = {
+        let mint_data = mint.try_borrow_data()?;
+        let mint_state = StateWithExtensions::<MintState>::unpack(&mint_data)?;
+        mint_state.get_extension::<TransferFeeConfig>().is_ok()
+    };
+    if !has_transfer_fee {
+        return Ok(());
+    }
+    require!(
+        mint.is_writable,
+        anchor_lang::error::ErrorCode::AccountNotMutable
+    );
+
+    let cpi_accounts = HarvestWithheldTokensToMint {
+        token_program_id: token_program.clone(),
+        mint,
+    };
+
+    let cpi_ctx = CpiContext::new(token_program, cpi_accounts);
+
+    harvest_withheld_tokens_to_mint(cpi_ctx, vec![account])
+}
+
+/// `transfer_checked` that resolves the mint's transfer hook, if it has one,
+/// from the context's remaining accounts and forwards its extra accounts.
+pub fn transfer_checked_with_hook<'info>(
+    ctx: CpiContext<'_, '_, '_, 'info, TransferChecked<'info>>,
+    amount: u64,
+    decimals: u8,
+) -> Result<()> {
+    invoke_transfer_checked(
+        ctx.program.key,
+        ctx.accounts.from,
+        ctx.accounts.mint,
+        ctx.accounts.to,
+        ctx.accounts.authority,
+        &ctx.remaining_accounts,
+        amount,
+        decimals,
+        ctx.signer_seeds,
+    )
+    .map_err(Into::into)
+}
+
+/// The accounts of an instruction refunding an escrow, as `refund_escrow` needs them.
+pub struct RefundEscrow<'a, 'info> {
+    pub escrow: &'a Account<'info, Escrow>,
+    pub mint_a: &'a InterfaceAccount<'info, Mint>,
+    pub maker: AccountInfo<'info>,
+    pub maker_ata_a: Option<&'a InterfaceAccount<'info, TokenAccount>>,
+    pub vault: Option<&'a InterfaceAccount<'info, TokenAccount>>,
+    pub rent_payer: AccountInfo<'info>,
+    pub token_program: AccountInfo<'info>,
+}
+
+/// Returns an escrow's whole deposit to its maker and closes the vault, sending
+/// the vault's rent to `rent_payer`. Returns the amount refunded.
+///
+/// A native deposit is held by the escrow itself above its rent, and is moved
+/// out here so that closing the escrow only returns the rent.
+pub fn refund_escrow<'info>(
+    accounts: RefundEscrow<'_, 'info>,
+    remaining_accounts: &[AccountInfo<'info>],
+) -> Result<u64> {
+    let RefundEscrow {
+        escrow,
+        mint_a,
+        maker,
+        maker_ata_a,
+        vault,
+        rent_payer,
+        token_program,
+    } = accounts;
+
+    if is_native(&mint_a.key()) {
+        let amount = lamports_above_rent(&escrow.to_account_info())?;
+        escrow.sub_lamports(amount)?;
+        maker.add_lamports(amount)?;
+        return Ok(amount);
+    }
+
+    let vault = required(vault)?;
+    let amount = vault.amount;
+
+    let signer_seeds: [&[&[u8]]; 1] = [&[
+        b"escrow",
+        escrow.maker.as_ref(),
+        &escrow.seed.to_le_bytes()[..],
+        &[escrow.bump],
+    ]];
+
+    let cpi_accounts = TransferChecked {
+        from: vault.to_account_info(),
+        to: required(maker_ata_a)?.to_account_info(),
+        mint: mint_a.to_account_info(),
+        authority: escrow.to_account_info(),
+    };
+
+    let cpi_context =
+        CpiContext::new_with_signer(token_program.clone(), cpi_accounts, &signer_seeds)
+            .with_remaining_accounts(remaining_accounts.to_vec());
+
+    transfer_checked_with_hook(cpi_context, amount, mint_a.decimals)?;
+
+    harvest_withheld_fees(
+        token_program.clone(),
+        mint_a.to_account_info(),
+        vault.to_account_info(),
+    )?;
+
+    let cpi_accounts = CloseAccount {
+        account: vault.to_account_info(),
+        destination: rent_payer,
+        authority: escrow.to_account_info(),
+    };
+
+    let cpi_context = CpiContext::new_with_signer(token_program, cpi_accounts, &signer_seeds);
+
+    close_account(cpi_context)?;
+
+    Ok(amount)
+}
+
+/// Returns the `MINT_RISK_*` flags for `mint`, rejecting mints whose tokens
+/// cannot be moved out of an escrow at all.
+pub fn mint_risk(mint: &AccountInfo) -> Result<u8> {
+    let mint_data = mint.try_borrow_data()?;
+    let mint_state = StateWithExtensions::<MintState>::unpack(&mint_data)?;
+
+    require!(
+        mint_state.get_extension::<NonTransferable>().is_err(),
+        ErrorCode::NonTransferableMint
+    );
+
+    let mut risk = 0;
+    if mint_state.base.freeze_authority.is_some() {
+        risk |= MINT_RISK_FREEZE_AUTHORITY;
+    }
+    if let Ok(extension) = mint_state.get_extension::<PermanentDelegate>() {
+        if Option::<Pubkey>::from(extension.delegate).is_some() {
+            risk |= MINT_RISK_PERMANENT_DELEGATE;
+        }
+    }
+    if let Ok(extension) = mint_state.get_extension::<MintCloseAuthority>() {
+        if Option::<Pubkey>::from(extension.close_authority).is_some() {
+            risk |= MINT_RISK_CLOSE_AUTHORITY;
+        }
+    }
+    if let Ok(extension) = mint_state.get_extension::<TransferHook>() {
+        if Option::<Pubkey>::from(extension.program_id).is_some() {
+            risk |= MINT_RISK_TRANSFER_HOOK;
+        }
+    }
+
+    Ok(risk)
+}
+
+/// Fails if `mint` carries a risk flag that `recorded` does not. Takers are
+/// quoted the risk recorded at make time, which the mint's authorities may have
+/// added to since.
+pub fn check_mint_risk(mint: &AccountInfo, recorded: u8) -> Result<()> {
+    require!(
+        mint_risk(mint)? & !recorded == 0,
+        ErrorCode::MintRiskChanged
+    );
+
+    Ok(())
+}
+
+/// Fails if the protocol fee is above the `max_fee_bps` the taker signed for,
+/// since the admin may raise it between the taker signing and the take landing.
+pub fn check_fee_cap(config: &Config, max_fee_bps: u16) -> Result<()> {
+    require!(config.fee_bps <= max_fee_bps, ErrorCode::FeeAboveMax);
+
+    Ok(())
+}
+
+/// Token balance of a vault just deposited into. Deposits are recorded from the
+/// balance rather than the amount sent, which overstates what landed whenever
+/// the mint charges a Token-2022 transfer fee.
+pub fn vault_balance(vault: &AccountInfo) -> Result<u64> {
+    let data = vault.try_borrow_data()?;
+    let state = StateWithExtensions::<TokenAccountState>::unpack(&data)?;
+
+    Ok(state.base.amount)
+}
+
+/// Splits a basket instruction's remaining accounts into one group of `size`
+/// accounts per leg, in leg order. Every group starts with `[mint, token_program]`,
+/// followed by the token accounts the instruction moves that leg between.
+pub fn basket_groups<'a, 'info>(
+    accounts: &'a [AccountInfo<'info>],
+    legs: &[BasketLeg],
+    size: usize,
+) -> Result<Vec<&'a [AccountInfo<'info>]>> {
+    require!(
+        accounts.len() == legs.len() * size,
+        ErrorCode::InvalidBasketAccounts
+    );
+
+    accounts
+        .chunks_exact(size)
+        .zip(legs)
+        .map(|(group, leg)| {
+            require_keys_eq!(group[0].key(), leg.mint, ErrorCode::ConstraintTokenMint);
+            require!(
+                group[1].key() == token::ID || group[1].key() == token_2022::ID,
+                ErrorCode::InvalidBasketAccounts
+            );
+            require_keys_eq!(
+                *group[0].owner,
+                group[1].key(),
+                ErrorCode::InvalidBasketAccounts
+            );
+            Ok(group)
+        })
+        .collect()
+}
+
+/// Reads the balance of a token account passed for a basket leg, checking its
+/// mint and owner since it did not go through account validation.
+pub fn basket_token_amount(account: &AccountInfo, mint: &Pubkey, owner: &Pubkey) -> Result<u64> {
+    require!(
+        *account.owner == token::ID || *account.owner == token_2022::ID,
+        ErrorCode::InvalidBasketAccounts
+    );
+
+    let data = account.try_borrow_data()?;
+    let state = StateWithExtensions::<TokenAccountState>::unpack(&data)?;
+    require_keys_eq!(state.base.mint, *mint, ErrorCode::ConstraintTokenMint);
+    require_keys_eq!(state.base.owner, *owner, ErrorCode::ConstraintTokenOwner);
+
+    Ok(state.base.amount)
+}
+
+/// `transfer_checked` for one basket leg. Transfer hooks are not resolved, so
+/// baskets cannot hold hooked mints.
+pub fn basket_transfer<'info>(
+    group: &[AccountInfo<'info>],
+    from: &AccountInfo<'info>,
+    to: &AccountInfo<'info>,
+    authority: &AccountInfo<'info>,
+    amount: u64,
+    signer_seeds: &[&[&[u8]]],
+) -> Result<()> {
+    let decimals = {
+        let mint_data = group[0].try_borrow_data()?;
+        StateWithExtensions::<MintState>::unpack(&mint_data)?
+            .base
+            .decimals
+    };
+
+    let cpi_accounts = TransferChecked {
+        from: from.clone(),
+        mint: group[0].clone(),
+        to: to.clone(),
+        authority: authority.clone(),
+    };
+
+    let cpi_ctx = CpiContext::new_with_signer(group[1].clone(), cpi_accounts, signer_seeds);
+
+    transfer_checked(cpi_ctx, amount, decimals)
+}
+
+/// Closes an emptied basket vault, returning its rent to `destination`. The
+/// group's mint must be writable if it has a transfer fee, for the fees withheld
+/// in the vault to be harvested.
+pub fn close_basket_vault<'info>(
+    group: &[AccountInfo<'info>],
+    vault: &AccountInfo<'info>,
+    destination: AccountInfo<'info>,
+    authority: AccountInfo<'info>,
+    signer_seeds: &[&[&[u8]]],
+) -> Result<()> {
+    harvest_withheld_fees(group[1].clone(), group[0].clone(), vault.clone())?;
+
+    let cpi_accounts = CloseAccount {
+        account: vault.clone(),
+        destination,
+        authority,
+    };
+
+    let cpi_ctx = CpiContext::new_with_signer(group[1].clone(), cpi_accounts, signer_seeds);
+
+    close_account(cpi_ctx)
+}
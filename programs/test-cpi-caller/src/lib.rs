@@ -0,0 +1,255 @@
+#![allow(unexpected_cfgs)]
+#![allow(deprecated)]
+
+//! Stand-in for a treasury program that trades through the escrow from its own
+//! PDA, so tests can check the escrow accepts program-owned makers and takers
+//! signing over CPI.
+
+use anchor_escrow::program::AnchorEscrow;
+use anchor_lang::prelude::*;
+
+mod tests;
+
+declare_id!("8Qkoqrwsz28NevSbM4BqibHB5RFzB6RDAcEvau7SWhgD");
+
+#[program]
+pub mod test_cpi_caller {
+    use super::*;
+
+    pub fn initialize(ctx: Context<Initialize>) -> Result<()> {
+        ctx.accounts.treasury.bump = ctx.bumps.treasury;
+
+        Ok(())
+    }
+
+    pub fn make(ctx: Context<Make>, seed: u64, deposit: u64, receive: u64) -> Result<()> {
+        let signer_seeds: [&[&[u8]]; 1] = [&[b"treasury", &[ctx.accounts.treasury.bump]]];
+
+        let cpi_accounts = anchor_escrow::cpi::accounts::Make {
+            maker: ctx.accounts.treasury.to_account_info(),
+            payer: ctx.accounts.payer.to_account_info(),
+            mint_a: ctx.accounts.mint_a.to_account_info(),
+            mint_b: ctx.accounts.mint_b.to_account_info(),
+            maker_ata_a: Some(ctx.accounts.treasury_ata_a.to_account_info()),
+            escrow: ctx.accounts.escrow.to_account_info(),
+            vault: Some(ctx.accounts.vault.to_account_info()),
+            config: ctx.accounts.config.to_account_info(),
+            associated_token_program: ctx.accounts.associated_token_program.to_account_info(),
+            token_program: ctx.accounts.token_program.to_account_info(),
+            system_program: ctx.accounts.system_program.to_account_info(),
+        };
+
+        let cpi_ctx = CpiContext::new_with_signer(
+            ctx.accounts.escrow_program.to_account_info(),
+            cpi_accounts,
+            &signer_seeds,
+        );
+
+        anchor_escrow::cpi::make(
+            cpi_ctx,
+            seed,
+            anchor_escrow::state::MakeTerms {
+                deposit,
+                receive,
+                receive_is_net: false,
+                not_before: None,
+                not_after: None,
+                allowed_taker: None,
+                taker_root: None,
+                pricing: anchor_escrow::state::Pricing::Fixed,
+            },
+        )
+    }
+
+    pub fn take(
+        ctx: Context<Take>,
+        amount: u64,
+        expected_deposit: u64,
+        expected_receive: u64,
+    ) -> Result<()> {
+        let signer_seeds: [&[&[u8]]; 1] = [&[b"treasury", &[ctx.accounts.treasury.bump]]];
+
+        let cpi_accounts = anchor_escrow::cpi::accounts::Take {
+            taker: ctx.accounts.treasury.to_account_info(),
+            maker: ctx.accounts.maker.to_account_info(),
+            rent_payer: ctx.accounts.rent_payer.to_account_info(),
+            mint_a: ctx.accounts.mint_a.to_account_info(),
+            mint_b: ctx.accounts.mint_b.to_account_info(),
+            taker_ata_a: Some(ctx.accounts.treasury_ata_a.to_account_info()),
+            taker_ata_b: Some(ctx.accounts.treasury_ata_b.to_account_info()),
+            maker_ata_a: None,
+            maker_ata_b: Some(ctx.accounts.maker_ata_b.to_account_info()),
+            escrow: ctx.accounts.escrow.to_account_info(),
+            vault: Some(ctx.accounts.vault.to_account_info()),
+            config: ctx.accounts.config.to_account_info(),
+            treasury_ata: None,
+            treasury: None,
+            price_feed: None,
+            clock: ctx.accounts.clock.to_account_info(),
+            associated_token_program: ctx.accounts.associated_token_program.to_account_info(),
+            token_program_a: ctx.accounts.token_program.to_account_info(),
+            token_program_b: ctx.accounts.token_program.to_account_info(),
+            system_program: ctx.accounts.system_program.to_account_info(),
+        };
+
+        let cpi_ctx = CpiContext::new_with_signer(
+            ctx.accounts.escrow_program.to_account_info(),
+            cpi_accounts,
+            &signer_seeds,
+        );
+
+        anchor_escrow::cpi::take(
+            cpi_ctx,
+            amount,
+            expected_deposit,
+            expected_receive,
+            0,
+            0,
+            vec![],
+        )
+    }
+
+    pub fn refund(ctx: Context<Refund>) -> Result<()> {
+        let signer_seeds: [&[&[u8]]; 1] = [&[b"treasury", &[ctx.accounts.treasury.bump]]];
+
+        let cpi_accounts = anchor_escrow::cpi::accounts::Refund {
+            maker: ctx.accounts.treasury.to_account_info(),
+            rent_payer: ctx.accounts.rent_payer.to_account_info(),
+            mint_a: ctx.accounts.mint_a.to_account_info(),
+            maker_ata_a: Some(ctx.accounts.treasury_ata_a.to_account_info()),
+            escrow: ctx.accounts.escrow.to_account_info(),
+            vault: Some(ctx.accounts.vault.to_account_info()),
+            token_program: ctx.accounts.token_program.to_account_info(),
+            system_program: ctx.accounts.system_program.to_account_info(),
+        };
+
+        let cpi_ctx = CpiContext::new_with_signer(
+            ctx.accounts.escrow_program.to_account_info(),
+            cpi_accounts,
+            &signer_seeds,
+        );
+
+        anchor_escrow::cpi::refund(cpi_ctx)
+    }
+}
+
+#[derive(Accounts)]
+pub struct Initialize<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    #[account(
+        init,
+        payer = payer,
+        seeds = [b"treasury"],
+        bump,
+        space = 8 + Treasury::INIT_SPACE,
+    )]
+    pub treasury: Account<'info, Treasury>,
+    pub system_program: Program<'info, System>,
+}
+
+/// The escrow's `Make` with the treasury PDA as maker; everything else is
+/// validated by the escrow program.
+#[derive(Accounts)]
+pub struct Make<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    #[account(mut, seeds = [b"treasury"], bump = treasury.bump)]
+    pub treasury: Account<'info, Treasury>,
+    /// CHECK: validated by the escrow program
+    pub mint_a: UncheckedAccount<'info>,
+    /// CHECK: validated by the escrow program
+    pub mint_b: UncheckedAccount<'info>,
+    /// CHECK: validated by the escrow program
+    #[account(mut)]
+    pub treasury_ata_a: UncheckedAccount<'info>,
+    /// CHECK: validated by the escrow program
+    #[account(mut)]
+    pub escrow: UncheckedAccount<'info>,
+    /// CHECK: validated by the escrow program
+    #[account(mut)]
+    pub vault: UncheckedAccount<'info>,
+    /// CHECK: validated by the escrow program
+    pub config: UncheckedAccount<'info>,
+    /// CHECK: validated by the escrow program
+    pub associated_token_program: UncheckedAccount<'info>,
+    /// CHECK: validated by the escrow program
+    pub token_program: UncheckedAccount<'info>,
+    pub system_program: Program<'info, System>,
+    pub escrow_program: Program<'info, AnchorEscrow>,
+}
+
+/// The escrow's `Take` with the treasury PDA as taker, for fee-free escrows
+/// whose legs share a token program.
+#[derive(Accounts)]
+pub struct Take<'info> {
+    #[account(mut, seeds = [b"treasury"], bump = treasury.bump)]
+    pub treasury: Account<'info, Treasury>,
+    /// CHECK: validated by the escrow program
+    #[account(mut)]
+    pub maker: UncheckedAccount<'info>,
+    /// CHECK: validated by the escrow program
+    #[account(mut)]
+    pub rent_payer: UncheckedAccount<'info>,
+    /// CHECK: validated by the escrow program
+    #[account(mut)]
+    pub mint_a: UncheckedAccount<'info>,
+    /// CHECK: validated by the escrow program
+    pub mint_b: UncheckedAccount<'info>,
+    /// CHECK: validated by the escrow program
+    #[account(mut)]
+    pub treasury_ata_a: UncheckedAccount<'info>,
+    /// CHECK: validated by the escrow program
+    #[account(mut)]
+    pub treasury_ata_b: UncheckedAccount<'info>,
+    /// CHECK: validated by the escrow program
+    #[account(mut)]
+    pub maker_ata_b: UncheckedAccount<'info>,
+    /// CHECK: validated by the escrow program
+    #[account(mut)]
+    pub escrow: UncheckedAccount<'info>,
+    /// CHECK: validated by the escrow program
+    #[account(mut)]
+    pub vault: UncheckedAccount<'info>,
+    /// CHECK: validated by the escrow program
+    pub config: UncheckedAccount<'info>,
+    pub clock: Sysvar<'info, Clock>,
+    /// CHECK: validated by the escrow program
+    pub associated_token_program: UncheckedAccount<'info>,
+    /// CHECK: validated by the escrow program
+    pub token_program: UncheckedAccount<'info>,
+    pub system_program: Program<'info, System>,
+    pub escrow_program: Program<'info, AnchorEscrow>,
+}
+
+/// The escrow's `Refund` with the treasury PDA as maker.
+#[derive(Accounts)]
+pub struct Refund<'info> {
+    #[account(mut, seeds = [b"treasury"], bump = treasury.bump)]
+    pub treasury: Account<'info, Treasury>,
+    /// CHECK: validated by the escrow program
+    #[account(mut)]
+    pub rent_payer: UncheckedAccount<'info>,
+    /// CHECK: validated by the escrow program
+    #[account(mut)]
+    pub mint_a: UncheckedAccount<'info>,
+    /// CHECK: validated by the escrow program
+    #[account(mut)]
+    pub treasury_ata_a: UncheckedAccount<'info>,
+    /// CHECK: validated by the escrow program
+    #[account(mut)]
+    pub escrow: UncheckedAccount<'info>,
+    /// CHECK: validated by the escrow program
+    #[account(mut)]
+    pub vault: UncheckedAccount<'info>,
+    /// CHECK: validated by the escrow program
+    pub token_program: UncheckedAccount<'info>,
+    pub system_program: Program<'info, System>,
+    pub escrow_program: Program<'info, AnchorEscrow>,
+}
+
+#[account]
+#[derive(InitSpace)]
+pub struct Treasury {
+    pub bump: u8,
+}
@@ -0,0 +1,342 @@
+#[cfg(test)]
+mod test {
+
+    use {
+        anchor_escrow::state::{Config, Escrow, MakeTerms, Pricing},
+        anchor_lang::{
+            prelude::Pubkey, solana_program::program_pack::Pack, AccountDeserialize,
+            AccountSerialize, InstructionData, Space, ToAccountMetas,
+        },
+        anchor_spl::{associated_token, token::spl_token},
+        litesvm::LiteSVM,
+        litesvm_token::{CreateAssociatedTokenAccount, CreateMint, MintTo},
+        solana_account::Account,
+        solana_address::Address,
+        solana_instruction::Instruction,
+        solana_keypair::Keypair,
+        solana_message::Message,
+        solana_native_token::LAMPORTS_PER_SOL,
+        solana_signer::Signer,
+        solana_transaction::Transaction,
+        std::path::PathBuf,
+    };
+
+    fn pubkey_to_addr(pk: &Pubkey) -> Address {
+        Address::from(pk.to_bytes())
+    }
+
+    fn addr_to_pubkey(addr: &Address) -> Pubkey {
+        Pubkey::new_from_array(addr.to_bytes())
+    }
+
+    fn to_instruction(
+        program_id: Pubkey,
+        accounts: Vec<anchor_lang::prelude::AccountMeta>,
+        data: Vec<u8>,
+    ) -> Instruction {
+        Instruction {
+            program_id: pubkey_to_addr(&program_id),
+            accounts: accounts
+                .into_iter()
+                .map(|m| solana_instruction::AccountMeta {
+                    pubkey: pubkey_to_addr(&m.pubkey),
+                    is_signer: m.is_signer,
+                    is_writable: m.is_writable,
+                })
+                .collect(),
+            data,
+        }
+    }
+
+    /// Sends `ix` signed and paid for by `signer`, failing the test if it fails.
+    fn send(program: &mut LiteSVM, ix: Instruction, signer: &Keypair) {
+        let message = Message::new(&[ix], Some(&signer.pubkey()));
+        let transaction = Transaction::new(&[signer], message, program.latest_blockhash());
+        program.send_transaction(transaction).unwrap();
+        program.expire_blockhash();
+    }
+
+    fn add_program(program: &mut LiteSVM, program_id: Pubkey, name: &str) {
+        let so_path = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+            .join(format!("../../target/deploy/{name}.so"));
+        let program_data = std::fs::read(so_path).expect("Failed to read program SO file");
+        let _ = program.add_program(pubkey_to_addr(&program_id), &program_data);
+    }
+
+    /// Writes a fee-free escrow config straight into the SVM, since
+    /// `initialize_config` needs the escrow deployed under the upgradeable loader.
+    fn set_config(program: &mut LiteSVM, admin: Pubkey) -> Pubkey {
+        let (config, bump) = Pubkey::find_program_address(&[b"config"], &anchor_escrow::ID);
+
+        let mut data = Vec::with_capacity(8 + Config::INIT_SPACE);
+        Config {
+            admin,
+            treasury: admin,
+            fee_bps: 0,
+            fee_on_mint_a: false,
+            paused: 0,
+            bump,
+        }
+        .try_serialize(&mut data)
+        .unwrap();
+
+        program
+            .set_account(
+                pubkey_to_addr(&config),
+                Account {
+                    lamports: program.minimum_balance_for_rent_exemption(data.len()),
+                    data,
+                    owner: pubkey_to_addr(&anchor_escrow::ID),
+                    executable: false,
+                    rent_epoch: 0,
+                },
+            )
+            .unwrap();
+
+        config
+    }
+
+    fn token_amount(program: &LiteSVM, account: &Address) -> u64 {
+        let account = program.get_account(account).unwrap();
+        spl_token::state::Account::unpack(&account.data)
+            .unwrap()
+            .amount
+    }
+
+    fn escrow_pda(maker: &Pubkey, seed: u64) -> Pubkey {
+        Pubkey::find_program_address(
+            &[b"escrow", maker.as_ref(), &seed.to_le_bytes()],
+            &anchor_escrow::ID,
+        )
+        .0
+    }
+
+    #[test]
+    fn test_pda_maker_via_cpi() {
+        let mut program = LiteSVM::new();
+        add_program(&mut program, anchor_escrow::ID, "anchor_escrow");
+        add_program(&mut program, crate::ID, "test_cpi_caller");
+
+        let maker = Keypair::new();
+        let taker = Keypair::new();
+        for wallet in [&maker, &taker] {
+            program
+                .airdrop(&wallet.pubkey(), 10 * LAMPORTS_PER_SOL)
+                .unwrap();
+        }
+        let maker_key = addr_to_pubkey(&maker.pubkey());
+        let config = set_config(&mut program, maker_key);
+
+        let treasury = Pubkey::find_program_address(&[b"treasury"], &crate::ID).0;
+        let init_ix = to_instruction(
+            crate::ID,
+            crate::accounts::Initialize {
+                payer: maker_key,
+                treasury,
+                system_program: anchor_lang::system_program::ID,
+            }
+            .to_account_metas(None),
+            crate::instruction::Initialize.data(),
+        );
+        send(&mut program, init_ix, &maker);
+
+        let mint_a = CreateMint::new(&mut program, &maker)
+            .decimals(6)
+            .authority(&maker.pubkey())
+            .send()
+            .unwrap();
+        let mint_b = CreateMint::new(&mut program, &maker)
+            .decimals(6)
+            .authority(&maker.pubkey())
+            .send()
+            .unwrap();
+
+        let mut ata = |mint: &Address, owner: &Address, amount: u64| {
+            let ata = CreateAssociatedTokenAccount::new(&mut program, &maker, mint)
+                .owner(owner)
+                .send()
+                .unwrap();
+            if amount > 0 {
+                MintTo::new(&mut program, &maker, mint, &ata, amount)
+                    .send()
+                    .unwrap();
+            }
+            ata
+        };
+        let maker_ata_a = ata(&mint_a, &maker.pubkey(), 1000);
+        let maker_ata_b = ata(&mint_b, &maker.pubkey(), 0);
+        let taker_ata_a = ata(&mint_a, &taker.pubkey(), 0);
+        let taker_ata_b = ata(&mint_b, &taker.pubkey(), 1000);
+        let treasury_ata_a = ata(&mint_a, &pubkey_to_addr(&treasury), 100);
+        let treasury_ata_b = ata(&mint_b, &pubkey_to_addr(&treasury), 100);
+
+        // The treasury posts an offer from its PDA, with the maker's wallet paying rent
+        let seed = 7u64;
+        let escrow = escrow_pda(&treasury, seed);
+        let vault =
+            associated_token::get_associated_token_address(&escrow, &addr_to_pubkey(&mint_a));
+
+        let make_ix = to_instruction(
+            crate::ID,
+            crate::accounts::Make {
+                payer: maker_key,
+                treasury,
+                mint_a: addr_to_pubkey(&mint_a),
+                mint_b: addr_to_pubkey(&mint_b),
+                treasury_ata_a: addr_to_pubkey(&treasury_ata_a),
+                escrow,
+                vault,
+                config,
+                associated_token_program: associated_token::spl_associated_token_account::ID,
+                token_program: spl_token::ID,
+                system_program: anchor_lang::system_program::ID,
+                escrow_program: anchor_escrow::ID,
+            }
+            .to_account_metas(None),
+            crate::instruction::Make {
+                seed,
+                deposit: 100,
+                receive: 50,
+            }
+            .data(),
+        );
+        send(&mut program, make_ix, &maker);
+
+        let escrow_account = program.get_account(&pubkey_to_addr(&escrow)).unwrap();
+        let escrow_data = Escrow::try_deserialize(&mut escrow_account.data.as_ref()).unwrap();
+        assert_eq!(escrow_data.maker, treasury);
+        assert_eq!(escrow_data.rent_payer, maker_key);
+        assert_eq!(token_amount(&program, &pubkey_to_addr(&vault)), 100);
+
+        // An ordinary taker fills part of it, paying the program-owned maker
+        let take_ix = to_instruction(
+            anchor_escrow::ID,
+            anchor_escrow::accounts::Take {
+                taker: addr_to_pubkey(&taker.pubkey()),
+                maker: treasury,
+                rent_payer: maker_key,
+                mint_a: addr_to_pubkey(&mint_a),
+                mint_b: addr_to_pubkey(&mint_b),
+                taker_ata_a: Some(addr_to_pubkey(&taker_ata_a)),
+                taker_ata_b: Some(addr_to_pubkey(&taker_ata_b)),
+                maker_ata_a: None,
+                maker_ata_b: Some(addr_to_pubkey(&treasury_ata_b)),
+                escrow,
+                vault: Some(vault),
+                config,
+                treasury_ata: None,
+                treasury: None,
+                price_feed: None,
+                clock: anchor_lang::solana_program::sysvar::clock::ID,
+                associated_token_program: associated_token::spl_associated_token_account::ID,
+                token_program_a: spl_token::ID,
+                token_program_b: spl_token::ID,
+                system_program: anchor_lang::system_program::ID,
+            }
+            .to_account_metas(None),
+            anchor_escrow::instruction::Take {
+                amount: 40,
+                expected_deposit: 100,
+                expected_receive: 50,
+                max_fee_bps: 0,
+                payment_option: 0,
+                proof: vec![],
+            }
+            .data(),
+        );
+        send(&mut program, take_ix, &taker);
+        assert_eq!(token_amount(&program, &treasury_ata_b), 120);
+
+        // The treasury takes the rest back
+        let refund_ix = to_instruction(
+            crate::ID,
+            crate::accounts::Refund {
+                treasury,
+                rent_payer: maker_key,
+                mint_a: addr_to_pubkey(&mint_a),
+                treasury_ata_a: addr_to_pubkey(&treasury_ata_a),
+                escrow,
+                vault,
+                token_program: spl_token::ID,
+                system_program: anchor_lang::system_program::ID,
+                escrow_program: anchor_escrow::ID,
+            }
+            .to_account_metas(None),
+            crate::instruction::Refund.data(),
+        );
+        send(&mut program, refund_ix, &maker);
+        assert_eq!(token_amount(&program, &treasury_ata_a), 60);
+        assert!(program.get_account(&pubkey_to_addr(&escrow)).is_none());
+
+        // And fills someone else's offer as a taker
+        let escrow = escrow_pda(&maker_key, 123);
+        let vault =
+            associated_token::get_associated_token_address(&escrow, &addr_to_pubkey(&mint_a));
+        let make_ix = to_instruction(
+            anchor_escrow::ID,
+            anchor_escrow::accounts::Make {
+                maker: maker_key,
+                payer: maker_key,
+                mint_a: addr_to_pubkey(&mint_a),
+                mint_b: addr_to_pubkey(&mint_b),
+                maker_ata_a: Some(addr_to_pubkey(&maker_ata_a)),
+                escrow,
+                vault: Some(vault),
+                config,
+                associated_token_program: associated_token::spl_associated_token_account::ID,
+                token_program: spl_token::ID,
+                system_program: anchor_lang::system_program::ID,
+            }
+            .to_account_metas(None),
+            anchor_escrow::instruction::Make {
+                seed: 123,
+                terms: MakeTerms {
+                    deposit: 10,
+                    receive: 20,
+                    receive_is_net: false,
+                    not_before: None,
+                    not_after: None,
+                    allowed_taker: None,
+                    taker_root: None,
+                    pricing: Pricing::Fixed,
+                },
+            }
+            .data(),
+        );
+        send(&mut program, make_ix, &maker);
+
+        let take_ix = to_instruction(
+            crate::ID,
+            crate::accounts::Take {
+                treasury,
+                maker: maker_key,
+                rent_payer: maker_key,
+                mint_a: addr_to_pubkey(&mint_a),
+                mint_b: addr_to_pubkey(&mint_b),
+                treasury_ata_a: addr_to_pubkey(&treasury_ata_a),
+                treasury_ata_b: addr_to_pubkey(&treasury_ata_b),
+                maker_ata_b: addr_to_pubkey(&maker_ata_b),
+                escrow,
+                vault,
+                config,
+                clock: anchor_lang::solana_program::sysvar::clock::ID,
+                associated_token_program: associated_token::spl_associated_token_account::ID,
+                token_program: spl_token::ID,
+                system_program: anchor_lang::system_program::ID,
+                escrow_program: anchor_escrow::ID,
+            }
+            .to_account_metas(None),
+            crate::instruction::Take {
+                amount: 10,
+                expected_deposit: 10,
+                expected_receive: 20,
+            }
+            .data(),
+        );
+        send(&mut program, take_ix, &maker);
+
+        assert_eq!(token_amount(&program, &treasury_ata_a), 70);
+        assert_eq!(token_amount(&program, &treasury_ata_b), 100);
+        assert_eq!(token_amount(&program, &maker_ata_b), 20);
+    }
+}
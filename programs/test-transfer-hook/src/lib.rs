@@ -0,0 +1,94 @@
+#![allow(unexpected_cfgs)]
+#![allow(deprecated)]
+
+//! Transfer hook that counts every transfer it sees in a PDA, so tests can
+//! check that the escrow forwarded the hook's extra accounts.
+
+use anchor_lang::prelude::*;
+use spl_discriminator::SplDiscriminate;
+use spl_tlv_account_resolution::{
+    account::ExtraAccountMeta, seeds::Seed, state::ExtraAccountMetaList,
+};
+use spl_transfer_hook_interface::instruction::ExecuteInstruction;
+
+declare_id!("GDkP5EycWNbdrDDjths9bttF1ZYZx4eZJZthN6yPFSMd");
+
+#[program]
+pub mod test_transfer_hook {
+    use super::*;
+
+    pub fn initialize_extra_account_meta_list(
+        ctx: Context<InitializeExtraAccountMetaList>,
+    ) -> Result<()> {
+        let extra_account_metas = [ExtraAccountMeta::new_with_seeds(
+            &[Seed::Literal {
+                bytes: b"counter".to_vec(),
+            }],
+            false,
+            true,
+        )?];
+
+        ExtraAccountMetaList::init::<ExecuteInstruction>(
+            &mut ctx.accounts.extra_account_meta_list.try_borrow_mut_data()?,
+            &extra_account_metas,
+        )?;
+
+        Ok(())
+    }
+
+    #[instruction(discriminator = ExecuteInstruction::SPL_DISCRIMINATOR_SLICE)]
+    pub fn transfer_hook(ctx: Context<TransferHook>, _amount: u64) -> Result<()> {
+        ctx.accounts.counter.transfers += 1;
+
+        Ok(())
+    }
+}
+
+#[derive(Accounts)]
+pub struct InitializeExtraAccountMetaList<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    /// CHECK: initialised as an ExtraAccountMetaList TLV buffer
+    #[account(
+        init,
+        payer = payer,
+        seeds = [b"extra-account-metas", mint.key().as_ref()],
+        bump,
+        space = ExtraAccountMetaList::size_of(1)?,
+    )]
+    pub extra_account_meta_list: UncheckedAccount<'info>,
+    /// CHECK: only used as a seed
+    pub mint: UncheckedAccount<'info>,
+    #[account(
+        init_if_needed,
+        payer = payer,
+        seeds = [b"counter"],
+        bump,
+        space = 8 + Counter::INIT_SPACE,
+    )]
+    pub counter: Account<'info, Counter>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct TransferHook<'info> {
+    /// CHECK: source token account, validated by Token-2022
+    pub source: UncheckedAccount<'info>,
+    /// CHECK: mint, validated by Token-2022
+    pub mint: UncheckedAccount<'info>,
+    /// CHECK: destination token account, validated by Token-2022
+    pub destination: UncheckedAccount<'info>,
+    /// CHECK: source authority, validated by Token-2022
+    pub owner: UncheckedAccount<'info>,
+    /// CHECK: ExtraAccountMetaList for the mint
+    #[account(seeds = [b"extra-account-metas", mint.key().as_ref()], bump)]
+    pub extra_account_meta_list: UncheckedAccount<'info>,
+    #[account(mut, seeds = [b"counter"], bump)]
+    pub counter: Account<'info, Counter>,
+}
+
+#[account]
+#[derive(InitSpace)]
+pub struct Counter {
+    pub transfers: u64,
+}